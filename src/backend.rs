@@ -0,0 +1,123 @@
+//! The ways Half-Life can be started, picked per profile at runtime.
+//!
+//! Every backend shares validation and ends up with a [`LaunchPlan`], only building the plan and
+//! spawning it differ.
+
+use std::path::Path;
+
+use crate::{config::Config, error::LauncherError, launch::LaunchPlan};
+
+#[cfg(not(windows))]
+pub use crate::linux::GameProcess;
+#[cfg(windows)]
+pub use crate::windows::GameProcess;
+
+pub trait GameLauncher {
+    fn name(&self) -> &'static str;
+
+    /// Whether the game and everything loaded into it are Windows files
+    fn uses_windows_files(&self) -> bool;
+
+    /// Resolves how the game is started. The config is already trimmed and validated.
+    fn build_plan(&self, config: &Config) -> Result<LaunchPlan, LauncherError>;
+
+    /// Starts the game from the plan `build_plan` made.
+    fn spawn(&self, config: &Config, plan: LaunchPlan) -> Result<GameProcess, LauncherError>;
+}
+
+/// The backend a profile asks for.
+#[cfg(not(windows))]
+pub fn launcher_for(config: &Config) -> Box<dyn GameLauncher> {
+    use crate::{
+        config::{SteamRuntime, WineRunner},
+        linux::{NativeLinux, SteamRuntimeLauncher},
+        wine::{ProtonLauncher, WineLauncher},
+    };
+
+    if config.use_wine {
+        return match config.wine_runner {
+            WineRunner::Wine => Box::new(WineLauncher),
+            WineRunner::Proton => Box::new(ProtonLauncher),
+        };
+    }
+
+    match config.steam_runtime {
+        SteamRuntime::Direct => Box::new(NativeLinux),
+        runtime => Box::new(SteamRuntimeLauncher(runtime)),
+    }
+}
+
+/// The backend a profile asks for.
+#[cfg(windows)]
+pub fn launcher_for(_config: &Config) -> Box<dyn GameLauncher> {
+    Box::new(crate::windows::WindowsLauncher)
+}
+
+/// Checks shared by every backend, including that the game files are built for the platform
+/// the backend runs them on.
+pub fn validate(config: &Config, launcher: &dyn GameLauncher) -> Result<(), LauncherError> {
+    config.validate()?;
+
+    for path in config.game_files() {
+        check_game_file(path, launcher)?;
+    }
+
+    Ok(())
+}
+
+/// That the game or a library is there and built for the platform `launcher` runs it on.
+pub fn check_game_file(path: &Path, launcher: &dyn GameLauncher) -> Result<(), LauncherError> {
+    if !path.is_file() {
+        return Err(LauncherError::FileDoesNotExist { path: path.into() });
+    }
+
+    // catch 64-bit or foreign builds here, they fail without a word once loaded
+    if launcher.uses_windows_files() {
+        crate::pe::expect_x86(path)?;
+    } else {
+        #[cfg(not(windows))]
+        crate::elf::expect_i386(path)?;
+    }
+
+    Ok(())
+}
+
+/// What the profile would run, without running it.
+pub fn launch_plan(config: &Config) -> Result<LaunchPlan, LauncherError> {
+    let config = config.trim();
+    let launcher = launcher_for(&config);
+
+    plan_with(&config, launcher.as_ref())
+}
+
+pub fn run_bxt(config: &Config) -> Result<GameProcess, LauncherError> {
+    let config = config.trim();
+    let launcher = launcher_for(&config);
+    let plan = plan_with(&config, launcher.as_ref())?;
+
+    launcher.spawn(&config, plan)
+}
+
+/// Starts a plan made before as it is, only checking the game files are still fine.
+pub fn run_plan(config: &Config, plan: LaunchPlan) -> Result<GameProcess, LauncherError> {
+    let config = config.trim();
+    let launcher = launcher_for(&config);
+
+    validate(&config, launcher.as_ref())?;
+
+    launcher.spawn(&config, plan)
+}
+
+fn plan_with(config: &Config, launcher: &dyn GameLauncher) -> Result<LaunchPlan, LauncherError> {
+    validate(config, launcher)?;
+
+    #[cfg_attr(windows, allow(unused_mut))]
+    let mut plan = launcher.build_plan(config)?;
+
+    #[cfg(not(windows))]
+    if let Some(allowlist) = config.environment_allowlist() {
+        plan.env_allowlist(allowlist);
+    }
+
+    Ok(plan)
+}
@@ -4,46 +4,292 @@ use std::{
     hash::{DefaultHasher, Hash, Hasher},
     io::{Read, Write},
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use serde::{Deserialize, Serialize};
 
-use crate::error::LauncherError;
+use crate::error::{ErrorContext, LauncherError};
+#[cfg(not(windows))]
+use crate::scheduling::IoClass;
 
-#[derive(Deserialize, Serialize, Clone)]
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
 pub struct Config {
-    pub hlexe: String,
-    pub bxt: String,
+    #[serde(with = "crate::os_serde")]
+    pub hlexe: PathBuf,
+    #[serde(with = "crate::os_serde")]
+    pub bxt: PathBuf,
     pub enable_bxt: bool,
-    pub bxt_rs: String,
+    #[serde(with = "crate::os_serde")]
+    pub bxt_rs: PathBuf,
     pub enable_bxt_rs: bool,
     pub gamemod: String,
     pub extras: String,
-    // unused features
+    /// More libraries loaded after BunnymodXT, in order. Preloaded on Linux and injected on
+    /// Windows.
+    #[serde(default)]
+    pub plugins: Vec<Plugin>,
+    /// Commands run before the game starts, one per line. A failing one aborts the launch.
+    #[serde(default)]
+    pub pre_launch_hooks: String,
+    /// Commands run after the game exits, one per line
+    #[serde(default)]
+    pub post_exit_hooks: String,
+    /// Nice level of the game, from -20 to 19
+    #[serde(default)]
+    pub nice: i32,
+    /// CPUs the game may run on, like `0-3,6`. Empty for all of them.
+    #[serde(default)]
+    pub cpu_affinity: String,
+    /// Seconds BunnymodXT and bxt-rs get to load, 0 to wait forever
+    #[serde(default = "default_startup_timeout")]
+    pub startup_timeout: u32,
+    /// Kill the game when it runs into `startup_timeout`
+    #[serde(default)]
+    pub kill_on_timeout: bool,
     #[cfg(not(windows))]
     pub use_wine: bool,
+    /// What runs `hl.exe` when `use_wine` is set
+    #[cfg(not(windows))]
+    #[serde(default)]
+    pub wine_runner: WineRunner,
+    /// Wine binary name searched in `PATH`, or a full path to one
+    #[cfg(not(windows))]
+    #[serde(default, with = "crate::os_serde")]
+    pub wine_binary: PathBuf,
+    #[cfg(not(windows))]
+    #[serde(default, with = "crate::os_serde")]
+    pub wine_prefix: PathBuf,
+    #[cfg(not(windows))]
+    #[serde(default)]
+    pub wine_dll_overrides: String,
+    #[cfg(not(windows))]
+    #[serde(default = "default_wine_debug")]
+    pub wine_debug: String,
+    /// Windows build of the launcher, run inside the Wine prefix to inject BunnymodXT, bxt-rs and
+    /// plugins
+    #[cfg(not(windows))]
+    #[serde(default, with = "crate::os_serde")]
+    pub wine_injector: PathBuf,
+    /// Proton installation directory, the one containing the `proton` script
+    #[cfg(not(windows))]
+    #[serde(default, with = "crate::os_serde")]
+    pub proton: PathBuf,
+    /// `STEAM_COMPAT_DATA_PATH`, derived from the Steam library of `hlexe` if empty
+    #[cfg(not(windows))]
+    #[serde(default, with = "crate::os_serde")]
+    pub proton_compat_data: PathBuf,
+    #[cfg(not(windows))]
+    #[serde(default)]
+    pub steam_runtime: SteamRuntime,
+    /// Script used when `steam_runtime` is [`SteamRuntime::Custom`]
+    #[cfg(not(windows))]
+    #[serde(default, with = "crate::os_serde")]
+    pub custom_runtime: PathBuf,
+    /// Ignore `LD_PRELOAD` and `LD_LIBRARY_PATH` of the launcher's own environment
+    #[cfg(not(windows))]
+    #[serde(default)]
+    pub strip_inherited_paths: bool,
+    /// Start the game from [`BASE_ENVIRONMENT`] and `environment_allowlist` instead of
+    /// everything the launcher has
+    #[cfg(not(windows))]
+    #[serde(default)]
+    pub clean_environment: bool,
+    /// More variable names kept in a clean environment, separated by spaces or commas
+    #[cfg(not(windows))]
+    #[serde(default)]
+    pub environment_allowlist: String,
+    #[cfg(not(windows))]
+    #[serde(default)]
+    pub io_class: IoClass,
+    /// Level within `io_class`, from 0 (highest) to 7
+    #[cfg(not(windows))]
+    #[serde(default = "default_io_level")]
+    pub io_level: u8,
+}
+
+/// Surrounding whitespace is dropped like for any other field, when the path is text at all.
+pub fn trim_path(path: &Path) -> PathBuf {
+    match path.to_str() {
+        Some(path) => PathBuf::from(path.trim()),
+        None => path.to_path_buf(),
+    }
+}
+
+fn default_startup_timeout() -> u32 {
+    30
+}
+
+/// Variables a clean environment keeps, enough for the game to find the display, audio, locale
+/// and the user's files.
+#[cfg(not(windows))]
+pub const BASE_ENVIRONMENT: &[&str] = &[
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "SHELL",
+    "PATH",
+    "TERM",
+    "TZ",
+    "LANG",
+    "LANGUAGE",
+    "LC_*",
+    "DISPLAY",
+    "WAYLAND_DISPLAY",
+    "XAUTHORITY",
+    "XDG_*",
+    "DBUS_SESSION_BUS_ADDRESS",
+    "PULSE_SERVER",
+    "PULSE_COOKIE",
+    "PIPEWIRE_RUNTIME_DIR",
+];
+
+/// Wine is very chatty otherwise, and the game output is kept
+#[cfg(not(windows))]
+fn default_wine_debug() -> String {
+    "-all".to_owned()
+}
+
+/// Same as the kernel gives a process with nice level 0
+#[cfg(not(windows))]
+fn default_io_level() -> u8 {
+    4
+}
+
+#[derive(Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct Plugin {
+    #[serde(with = "crate::os_serde")]
+    pub path: PathBuf,
+    pub enabled: bool,
+}
+
+/// How `hl.exe` is run on Linux.
+#[cfg(not(windows))]
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WineRunner {
+    #[default]
+    Wine,
+    Proton,
+}
+
+#[cfg(not(windows))]
+impl WineRunner {
+    pub const ALL: [Self; 2] = [Self::Wine, Self::Proton];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Wine => "Wine",
+            Self::Proton => "Proton",
+        }
+    }
+}
+
+/// Which runtime `hl_linux` is started inside of.
+#[cfg(not(windows))]
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SteamRuntime {
+    /// Steam Runtime 1 "scout", auto-detected from the usual `run.sh` locations
+    #[default]
+    Scout,
+    /// Steam Linux Runtime 3.0 "sniper", started through its pressure-vessel `_v2-entry-point`
+    Sniper,
+    /// User-specified script, called with the game executable and its arguments
+    Custom,
+    /// No runtime, `hl_linux` is started directly
+    Direct,
+}
+
+#[cfg(not(windows))]
+impl SteamRuntime {
+    pub const ALL: [Self; 4] = [Self::Scout, Self::Sniper, Self::Custom, Self::Direct];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Scout => "Steam Runtime (scout)",
+            Self::Sniper => "Steam Linux Runtime (sniper)",
+            Self::Custom => "Custom script",
+            Self::Direct => "None",
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize, Clone)]
 pub struct ConfigWithProfiles {
     pub current_profile: usize,
     pub configs: Vec<Config>,
+    #[serde(default)]
+    pub window: WindowState,
+}
+
+/// Launcher window as it was left, in logical pixels so it comes back the same at any zoom.
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq)]
+pub struct WindowState {
+    pub size: Option<[f32; 2]>,
+    /// Not every platform tells where the window is
+    pub position: Option<[f32; 2]>,
+    /// Derived from the monitor scale on the first start, ctrl +/- changes it
+    pub zoom: Option<f32>,
 }
 
 const CONFIG_FILE_NAME: &str = "bxt_launcher.toml";
 
+/// Directory of the launcher executable, where the config and everything else it writes goes.
+pub fn launcher_dir() -> PathBuf {
+    match env::current_exe() {
+        Ok(path) => path.parent().map(Path::to_path_buf).unwrap_or_default(),
+        Err(_) => PathBuf::new(),
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
-            hlexe: String::new(),
-            bxt: String::new(),
-            bxt_rs: String::new(),
+            hlexe: PathBuf::new(),
+            bxt: PathBuf::new(),
+            bxt_rs: PathBuf::new(),
             gamemod: "valve".to_owned(),
             extras: String::new(),
+            plugins: vec![],
+            pre_launch_hooks: String::new(),
+            post_exit_hooks: String::new(),
+            nice: 0,
+            cpu_affinity: String::new(),
+            startup_timeout: default_startup_timeout(),
+            kill_on_timeout: false,
             enable_bxt: false,
             enable_bxt_rs: false,
             #[cfg(not(windows))]
             use_wine: false,
+            #[cfg(not(windows))]
+            wine_runner: WineRunner::default(),
+            #[cfg(not(windows))]
+            wine_binary: PathBuf::new(),
+            #[cfg(not(windows))]
+            wine_prefix: PathBuf::new(),
+            #[cfg(not(windows))]
+            wine_dll_overrides: String::new(),
+            #[cfg(not(windows))]
+            wine_debug: default_wine_debug(),
+            #[cfg(not(windows))]
+            wine_injector: PathBuf::new(),
+            #[cfg(not(windows))]
+            proton: PathBuf::new(),
+            #[cfg(not(windows))]
+            proton_compat_data: PathBuf::new(),
+            #[cfg(not(windows))]
+            steam_runtime: SteamRuntime::default(),
+            #[cfg(not(windows))]
+            custom_runtime: PathBuf::new(),
+            #[cfg(not(windows))]
+            strip_inherited_paths: false,
+            #[cfg(not(windows))]
+            clean_environment: false,
+            #[cfg(not(windows))]
+            environment_allowlist: String::new(),
+            #[cfg(not(windows))]
+            io_class: IoClass::default(),
+            #[cfg(not(windows))]
+            io_level: default_io_level(),
         }
     }
 }
@@ -59,12 +305,12 @@ impl Config {
             ..
         } = self;
 
-        if hlexe.is_empty() {
+        if hlexe.as_os_str().is_empty() {
             return Err(LauncherError::NoHLExe);
         }
 
         if *enable_bxt_rs {
-            let path = Path::new(bxt_rs.as_str());
+            let path = bxt_rs.as_path();
 
             if !path.exists() || !path.is_file() {
                 return Err(LauncherError::FileDoesNotExist { path: path.into() });
@@ -73,16 +319,93 @@ impl Config {
 
         // BunnymodXT
         if *enable_bxt {
-            let path = Path::new(bxt.as_str());
+            let path = bxt.as_path();
 
             if !path.exists() || !path.is_file() {
                 return Err(LauncherError::FileDoesNotExist { path: path.into() });
             }
         }
 
+        for path in self.enabled_plugins() {
+            if !path.is_file() {
+                return Err(LauncherError::FileDoesNotExist { path: path.into() });
+            }
+        }
+
+        crate::scheduling::parse_cpu_list(&self.cpu_affinity)?;
+
         Ok(())
     }
 
+    /// Game executable followed by every library that is going to be loaded into it.
+    pub fn game_files(&self) -> Vec<&Path> {
+        let mut files = vec![self.hlexe.as_path()];
+
+        files.extend(self.libraries());
+
+        files
+    }
+
+    /// Every library loaded into the game, in load order: bxt-rs, BunnymodXT, then plugins.
+    pub fn libraries(&self) -> Vec<&Path> {
+        let mut libraries = vec![];
+
+        if self.enable_bxt_rs {
+            libraries.push(self.bxt_rs.as_path());
+        }
+
+        if self.enable_bxt {
+            libraries.push(self.bxt.as_path());
+        }
+
+        libraries.extend(self.enabled_plugins());
+
+        libraries
+    }
+
+    /// Plugins that are turned on and have a path.
+    pub fn enabled_plugins(&self) -> impl Iterator<Item = &Path> {
+        self.plugins
+            .iter()
+            .filter(|plugin| plugin.enabled && !trim_path(&plugin.path).as_os_str().is_empty())
+            .map(|plugin| plugin.path.as_path())
+    }
+
+    /// Folder the game executable is in, which the game runs from.
+    pub fn hl_root(&self) -> Result<&Path, LauncherError> {
+        self.hlexe
+            .parent()
+            .ok_or_else(|| LauncherError::NoGameDirectory {
+                path: self.hlexe.clone(),
+            })
+    }
+
+    pub fn startup_timeout(&self) -> Option<Duration> {
+        (self.startup_timeout > 0).then(|| Duration::from_secs(self.startup_timeout.into()))
+    }
+
+    /// Variables kept from the launcher's environment, `None` when everything is.
+    #[cfg(not(windows))]
+    pub fn environment_allowlist(&self) -> Option<Vec<String>> {
+        if !self.clean_environment {
+            return None;
+        }
+
+        let extra = self
+            .environment_allowlist
+            .split([' ', ','])
+            .filter(|name| !name.is_empty());
+
+        Some(
+            BASE_ENVIRONMENT
+                .iter()
+                .copied()
+                .chain(extra)
+                .map(str::to_owned)
+                .collect(),
+        )
+    }
+
     pub fn trim(&self) -> Self {
         let Self {
             hlexe,
@@ -92,20 +415,100 @@ impl Config {
             enable_bxt_rs,
             gamemod,
             extras,
+            plugins,
+            pre_launch_hooks,
+            post_exit_hooks,
+            nice,
+            cpu_affinity,
+            startup_timeout,
+            kill_on_timeout,
             #[cfg(not(windows))]
             use_wine,
+            #[cfg(not(windows))]
+            wine_runner,
+            #[cfg(not(windows))]
+            wine_binary,
+            #[cfg(not(windows))]
+            wine_prefix,
+            #[cfg(not(windows))]
+            wine_dll_overrides,
+            #[cfg(not(windows))]
+            wine_debug,
+            #[cfg(not(windows))]
+            wine_injector,
+            #[cfg(not(windows))]
+            proton,
+            #[cfg(not(windows))]
+            proton_compat_data,
+            #[cfg(not(windows))]
+            steam_runtime,
+            #[cfg(not(windows))]
+            custom_runtime,
+            #[cfg(not(windows))]
+            strip_inherited_paths,
+            #[cfg(not(windows))]
+            clean_environment,
+            #[cfg(not(windows))]
+            environment_allowlist,
+            #[cfg(not(windows))]
+            io_class,
+            #[cfg(not(windows))]
+            io_level,
         } = self;
 
         Self {
-            hlexe: hlexe.trim().to_owned(),
-            bxt: bxt.trim().to_owned(),
+            hlexe: trim_path(hlexe),
+            bxt: trim_path(bxt),
             enable_bxt: *enable_bxt,
-            bxt_rs: bxt_rs.trim().to_owned(),
+            bxt_rs: trim_path(bxt_rs),
             enable_bxt_rs: *enable_bxt_rs,
             gamemod: gamemod.trim().to_owned(),
             extras: extras.trim().to_owned(),
+            plugins: plugins
+                .iter()
+                .map(|plugin| Plugin {
+                    path: trim_path(&plugin.path),
+                    enabled: plugin.enabled,
+                })
+                .collect(),
+            pre_launch_hooks: pre_launch_hooks.clone(),
+            post_exit_hooks: post_exit_hooks.clone(),
+            nice: *nice,
+            cpu_affinity: cpu_affinity.trim().to_owned(),
+            startup_timeout: *startup_timeout,
+            kill_on_timeout: *kill_on_timeout,
             #[cfg(not(windows))]
             use_wine: *use_wine,
+            #[cfg(not(windows))]
+            wine_runner: *wine_runner,
+            #[cfg(not(windows))]
+            wine_binary: trim_path(wine_binary),
+            #[cfg(not(windows))]
+            wine_prefix: trim_path(wine_prefix),
+            #[cfg(not(windows))]
+            wine_dll_overrides: wine_dll_overrides.trim().to_owned(),
+            #[cfg(not(windows))]
+            wine_debug: wine_debug.trim().to_owned(),
+            #[cfg(not(windows))]
+            wine_injector: trim_path(wine_injector),
+            #[cfg(not(windows))]
+            proton: trim_path(proton),
+            #[cfg(not(windows))]
+            proton_compat_data: trim_path(proton_compat_data),
+            #[cfg(not(windows))]
+            steam_runtime: *steam_runtime,
+            #[cfg(not(windows))]
+            custom_runtime: trim_path(custom_runtime),
+            #[cfg(not(windows))]
+            strip_inherited_paths: *strip_inherited_paths,
+            #[cfg(not(windows))]
+            clean_environment: *clean_environment,
+            #[cfg(not(windows))]
+            environment_allowlist: environment_allowlist.trim().to_owned(),
+            #[cfg(not(windows))]
+            io_class: *io_class,
+            #[cfg(not(windows))]
+            io_level: *io_level,
         }
     }
 }
@@ -115,6 +518,7 @@ impl Default for ConfigWithProfiles {
         Self {
             current_profile: 0,
             configs: vec![Config::default(); 4],
+            window: WindowState::default(),
         }
     }
 }
@@ -123,10 +527,14 @@ impl ConfigWithProfiles {
     fn parse_from_file(path: impl AsRef<Path> + Into<PathBuf>) -> Result<Self, LauncherError> {
         let path = path.as_ref();
 
-        let mut file = OpenOptions::new().read(true).open(path.as_os_str())?;
+        let mut file = OpenOptions::new()
+            .read(true)
+            .open(path.as_os_str())
+            .context("open config file", path)?;
         let mut buffer = String::new();
 
-        file.read_to_string(&mut buffer)?;
+        file.read_to_string(&mut buffer)
+            .context("read config file", path)?;
 
         let config: ConfigWithProfiles = toml::from_str(&buffer)?;
 
@@ -140,21 +548,20 @@ impl ConfigWithProfiles {
             .create(true)
             .write(true)
             .truncate(true)
-            .open(path)?;
+            .open(path)
+            .context("create config file", path)?;
 
         let res = toml::to_string(&self)?;
 
-        file.write_all(res.as_bytes())?;
-        file.flush()?;
+        file.write_all(res.as_bytes())
+            .and_then(|_| file.flush())
+            .context("write config file", path)?;
 
         Ok(())
     }
 
     pub fn load_from_default() -> Result<Self, LauncherError> {
-        let path = match env::current_exe() {
-            Ok(path) => path.parent().unwrap().join(CONFIG_FILE_NAME),
-            Err(_) => PathBuf::from(CONFIG_FILE_NAME),
-        };
+        let path = launcher_dir().join(CONFIG_FILE_NAME);
 
         let res = Self::parse_from_file(&path);
 
@@ -170,17 +577,15 @@ impl ConfigWithProfiles {
             let hash_res = hasher.finish();
 
             let config_name = format!("{}_{}", hash_res, CONFIG_FILE_NAME);
-            std::fs::rename(&path, path.with_file_name(config_name))?;
+            std::fs::rename(&path, path.with_file_name(config_name))
+                .context("back up config file", &path)?;
         }
 
-        return res;
+        res
     }
 
     pub fn write_to_default(&self) -> Result<(), LauncherError> {
-        let path = match env::current_exe() {
-            Ok(path) => path.parent().unwrap().join(CONFIG_FILE_NAME),
-            Err(_) => PathBuf::from(CONFIG_FILE_NAME),
-        };
+        let path = launcher_dir().join(CONFIG_FILE_NAME);
 
         self.write_to_file(path)
     }
@@ -0,0 +1,134 @@
+use std::{fs, path::PathBuf, time::Duration};
+
+use crate::{
+    config::{Config, launcher_dir},
+    error::{ErrorContext, LauncherError},
+    launch::LaunchPlan,
+    session::{OutputLog, SessionInfo},
+};
+
+const CRASH_DIR: &str = "crashes";
+
+/// How much of the game output goes into a bundle
+const OUTPUT_LINES: usize = 200;
+
+/// Log files that might say what happened, relative to the Half-Life directory
+const GAME_LOGS: &[&str] = &["debug.log", "bxt-rs.log"];
+
+/// Same but relative to the game mod directory, `qconsole.log` is written with `-condebug`
+const MOD_LOGS: &[&str] = &["qconsole.log"];
+
+/// Games failing later than this were most likely played for a while, and the launch itself
+/// had nothing to do with it
+const CRASH_WINDOW: Duration = Duration::from_secs(120);
+
+/// Killed by a signal or exited with an error code within [`CRASH_WINDOW`] of starting. A clean
+/// quit from the game menu is 0.
+pub fn is_crash(session: &SessionInfo) -> bool {
+    let Some(status) = session.exit_status else {
+        return false;
+    };
+
+    let failed = status.signal.is_some() || status.code.is_some_and(|code| code != 0);
+
+    failed
+        && session
+            .duration()
+            .is_some_and(|duration| duration <= CRASH_WINDOW)
+}
+
+/// Saves everything needed for a bug report into a new timestamped directory under `crashes`
+/// next to the launcher, returning that directory.
+pub fn write_bundle(
+    session: &SessionInfo,
+    config: &Config,
+    plan: &LaunchPlan,
+    output: &OutputLog,
+) -> Result<PathBuf, LauncherError> {
+    let timestamp = jiff::Timestamp::try_from(session.started)
+        .map(|timestamp| timestamp.strftime("%Y%m%d-%H%M%S").to_string())
+        .unwrap_or_default();
+
+    let bundle = launcher_dir()
+        .join(CRASH_DIR)
+        .join(format!("crash-{timestamp}-profile{}", session.profile));
+
+    fs::create_dir_all(&bundle).context("create crash bundle folder", &bundle)?;
+
+    let write = |name: &str, contents: String| {
+        let path = bundle.join(name);
+        fs::write(&path, contents).context("write", &path)
+    };
+
+    write("summary.txt", summary(session))?;
+    write("launch_plan.txt", plan.to_string())?;
+    write("config.toml", toml::to_string(config)?)?;
+
+    let mut output = output.tail(OUTPUT_LINES).join("\n");
+    output.push('\n');
+    write("output.log", output)?;
+
+    for log in game_logs(config) {
+        // only logs touched during this session are relevant
+        let modified = fs::metadata(&log).and_then(|metadata| metadata.modified());
+
+        if !modified.is_ok_and(|modified| modified >= session.started) {
+            continue;
+        }
+
+        if let Some(name) = log.file_name() {
+            fs::copy(&log, bundle.join(name)).context("copy game log", &log)?;
+        }
+    }
+
+    Ok(bundle)
+}
+
+fn summary(session: &SessionInfo) -> String {
+    let time = |time| {
+        jiff::Timestamp::try_from(time)
+            .map(|timestamp| timestamp.to_string())
+            .unwrap_or_default()
+    };
+
+    let mut summary = format!(
+        "profile: {}\nhlexe: {}\ngamemod: {}\nstarted: {}\n",
+        session.profile,
+        session.hlexe.display(),
+        session.gamemod,
+        time(session.started)
+    );
+
+    if let Some(ended) = session.ended {
+        summary += &format!("ended: {}\n", time(ended));
+    }
+
+    if let Some(duration) = session.duration() {
+        summary += &format!("duration: {:.1} seconds\n", duration.as_secs_f32());
+    }
+
+    if let Some(exit_status) = session.exit_status {
+        summary += &format!("exit status: {exit_status}\n");
+    }
+
+    summary
+}
+
+fn game_logs(config: &Config) -> Vec<PathBuf> {
+    let Ok(hl_root) = config.hl_root() else {
+        return vec![];
+    };
+
+    let gamemod = if config.gamemod.is_empty() {
+        "valve"
+    } else {
+        &config.gamemod
+    };
+
+    GAME_LOGS
+        .iter()
+        .map(|log| hl_root.join(log))
+        .chain(MOD_LOGS.iter().map(|log| hl_root.join(gamemod).join(log)))
+        .filter(|path| path.is_file())
+        .collect()
+}
@@ -0,0 +1,100 @@
+use std::{fs::File, io::Read, path::Path};
+
+use crate::error::LauncherError;
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const HEADER_SIZE: usize = 20;
+
+const EM_386: u16 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElfClass {
+    Elf32,
+    Elf64,
+}
+
+/// The part of the ELF identification and header needed to tell what a file is built for.
+#[derive(Debug, Clone, Copy)]
+pub struct ElfHeader {
+    pub class: ElfClass,
+    pub machine: u16,
+}
+
+impl ElfHeader {
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < HEADER_SIZE || !bytes.starts_with(ELF_MAGIC) {
+            return None;
+        }
+
+        let class = match bytes[4] {
+            1 => ElfClass::Elf32,
+            2 => ElfClass::Elf64,
+            _ => return None,
+        };
+
+        let machine = [bytes[18], bytes[19]];
+        let machine = match bytes[5] {
+            1 => u16::from_le_bytes(machine),
+            2 => u16::from_be_bytes(machine),
+            _ => return None,
+        };
+
+        Some(Self { class, machine })
+    }
+
+    pub fn read(path: &Path) -> Result<Self, LauncherError> {
+        let mut file = File::open(path).map_err(|source| {
+            if source.kind() == std::io::ErrorKind::NotFound {
+                LauncherError::FileDoesNotExist { path: path.into() }
+            } else {
+                LauncherError::IOContext {
+                    operation: "read",
+                    path: path.into(),
+                    source,
+                }
+            }
+        })?;
+
+        let mut bytes = [0u8; HEADER_SIZE];
+        let not_elf = || LauncherError::NotElf { path: path.into() };
+
+        file.read_exact(&mut bytes).map_err(|_| not_elf())?;
+
+        Self::parse(&bytes).ok_or_else(not_elf)
+    }
+
+    pub fn is_i386(&self) -> bool {
+        self.class == ElfClass::Elf32 && self.machine == EM_386
+    }
+
+    pub fn describe(&self) -> String {
+        let bits = match self.class {
+            ElfClass::Elf32 => "32-bit",
+            ElfClass::Elf64 => "64-bit",
+        };
+
+        let machine = match self.machine {
+            EM_386 => "x86".to_owned(),
+            62 => "x86-64".to_owned(),
+            40 => "ARM".to_owned(),
+            183 => "AArch64".to_owned(),
+            machine => format!("machine {machine}"),
+        };
+
+        format!("{bits} {machine}")
+    }
+}
+
+/// Half-Life on Linux is only ever 32-bit x86, and so must be everything preloaded into it.
+pub fn expect_i386(path: &Path) -> Result<(), LauncherError> {
+    let header = ElfHeader::read(path)?;
+
+    if !header.is_i386() {
+        return Err(LauncherError::WrongElfArchitecture {
+            path: path.into(),
+            found: header.describe(),
+        });
+    }
+
+    Ok(())
+}
@@ -1,4 +1,6 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, trim_path};
 
 #[derive(Debug, thiserror::Error)]
 pub enum LauncherError {
@@ -11,13 +13,25 @@ pub enum LauncherError {
         source: windows::core::Error,
     },
     #[cfg(windows)]
-    #[error("Inject error: {source}")]
+    #[error("Cannot {operation} {path}: {source}")]
+    WindowsAPIContext {
+        operation: &'static str,
+        path: PathBuf,
+        #[source]
+        source: windows::core::Error,
+    },
+    #[cfg(windows)]
+    #[error("Cannot {operation} {path}: {source}")]
     InjectError {
+        operation: &'static str,
+        path: PathBuf,
         #[source]
         source: dll_syringe::error::InjectError,
     },
-    #[error("IO error: {source}")]
-    IOError {
+    #[error("Cannot {operation} {path}: {source}")]
+    IOContext {
+        operation: &'static str,
+        path: PathBuf,
         #[source]
         source: std::io::Error,
     },
@@ -28,14 +42,61 @@ pub enum LauncherError {
     TomlParsingError { source: toml::de::Error },
     #[error("Config writing error: {source}")]
     TomlWritingError { source: toml::ser::Error },
+    #[error("Cannot tell the Half-Life folder from {path}")]
+    NoGameDirectory { path: PathBuf },
     #[error("File does not exist: {path}")]
     FileDoesNotExist { path: PathBuf },
+    #[error("BXT failed to load: {reason}")]
+    BxtNotLoaded { reason: String },
+    #[error("Half-Life did not start within {seconds} seconds, still waiting for {waiting_for}")]
+    StartupTimeout { seconds: u32, waiting_for: String },
+    #[error("Half-Life exited with {status} before BXT loaded")]
+    GameExitedEarly { status: String },
+    #[error("Hook `{command}` failed with {status}")]
+    HookFailed { command: String, status: String },
+    #[error("Invalid injector arguments: {reason}")]
+    InvalidInjectorArguments { reason: String },
+    #[error("Invalid CPU list `{list}`, expected something like 0-3,6")]
+    InvalidCpuList { list: String },
+    #[cfg(not(windows))]
+    #[error("Cannot apply priority or CPU affinity: {source}")]
+    SchedulingFailed {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Cannot focus Half-Life: {reason}")]
+    CannotFocusGame { reason: String },
+    #[error("Not a Windows executable or library: {path}")]
+    NotPe { path: PathBuf },
+    #[error("{path} is {found}, Half-Life needs 32-bit x86")]
+    WrongPeArchitecture { path: PathBuf, found: String },
+    #[cfg(not(windows))]
+    #[error("Not a Linux executable or library: {path}")]
+    NotElf { path: PathBuf },
+    #[cfg(not(windows))]
+    #[error("{path} is {found}, Half-Life needs 32-bit x86")]
+    WrongElfArchitecture { path: PathBuf, found: String },
     #[cfg(not(windows))]
     #[error("Cannot find Steam path")]
     CannotFindSteam,
     #[cfg(not(windows))]
+    #[error("Cannot find Steam Linux Runtime (sniper)")]
+    CannotFindSniper,
+    #[cfg(not(windows))]
+    #[error("No given custom runtime script")]
+    NoCustomRuntime,
+    #[cfg(not(windows))]
     #[error("No wine installed")]
     NoWine,
+    #[cfg(not(windows))]
+    #[error("No given Windows build of the launcher to load BunnymodXT under Wine")]
+    NoWineInjector,
+    #[cfg(not(windows))]
+    #[error("No given Proton installation")]
+    NoProton,
+    #[cfg(not(windows))]
+    #[error("Cannot find Steam library of Half-Life for the Proton prefix, set it manually")]
+    NoProtonCompatData,
 }
 
 #[cfg(windows)]
@@ -45,16 +106,40 @@ impl From<windows::core::Error> for LauncherError {
     }
 }
 
-impl From<std::io::Error> for LauncherError {
-    fn from(value: std::io::Error) -> Self {
-        LauncherError::IOError { source: value }
+/// Says what was being done and to which file when a call fails.
+pub trait ErrorContext<T> {
+    fn context(self, operation: &'static str, path: impl AsRef<Path>) -> Result<T, LauncherError>;
+}
+
+impl<T> ErrorContext<T> for std::io::Result<T> {
+    fn context(self, operation: &'static str, path: impl AsRef<Path>) -> Result<T, LauncherError> {
+        self.map_err(|source| LauncherError::IOContext {
+            operation,
+            path: path.as_ref().to_owned(),
+            source,
+        })
+    }
+}
+
+#[cfg(windows)]
+impl<T> ErrorContext<T> for windows::core::Result<T> {
+    fn context(self, operation: &'static str, path: impl AsRef<Path>) -> Result<T, LauncherError> {
+        self.map_err(|source| LauncherError::WindowsAPIContext {
+            operation,
+            path: path.as_ref().to_owned(),
+            source,
+        })
     }
 }
 
 #[cfg(windows)]
-impl From<dll_syringe::error::InjectError> for LauncherError {
-    fn from(value: dll_syringe::error::InjectError) -> Self {
-        LauncherError::InjectError { source: value }
+impl<T> ErrorContext<T> for Result<T, dll_syringe::error::InjectError> {
+    fn context(self, operation: &'static str, path: impl AsRef<Path>) -> Result<T, LauncherError> {
+        self.map_err(|source| LauncherError::InjectError {
+            operation,
+            path: path.as_ref().to_owned(),
+            source,
+        })
     }
 }
 
@@ -69,3 +154,254 @@ impl From<toml::ser::Error> for LauncherError {
         Self::TomlWritingError { source: value }
     }
 }
+
+/// Setting an error is about, so the UI can take the user there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Field {
+    HlExe,
+    Bxt,
+    BxtRs,
+    /// Index into the plugin list.
+    Plugin(usize),
+    Gamemod,
+    Extras,
+    PreLaunchHooks,
+    PostExitHooks,
+    Priority,
+    CpuAffinity,
+    StartupTimeout,
+    #[cfg(not(windows))]
+    WineBinary,
+    #[cfg(not(windows))]
+    WinePrefix,
+    #[cfg(not(windows))]
+    WineDllOverrides,
+    #[cfg(not(windows))]
+    WineDebug,
+    #[cfg(not(windows))]
+    WineInjector,
+    #[cfg(not(windows))]
+    Proton,
+    #[cfg(not(windows))]
+    ProtonCompatData,
+    #[cfg(not(windows))]
+    Runtime,
+    #[cfg(not(windows))]
+    CustomRuntime,
+    #[cfg(not(windows))]
+    EnvironmentAllowlist,
+}
+
+/// What to do about an error.
+#[derive(Debug, Clone)]
+pub struct Hint {
+    pub text: String,
+    pub field: Option<Field>,
+}
+
+impl Hint {
+    fn new(text: impl Into<String>, field: impl Into<Option<Field>>) -> Self {
+        Self {
+            text: text.into(),
+            field: field.into(),
+        }
+    }
+}
+
+impl LauncherError {
+    /// How to get past the error, pointing at the setting to change when there is one.
+    ///
+    /// `config` is the profile that was launched, to tell which setting a path came from.
+    pub fn hint(&self, config: &Config) -> Hint {
+        match self {
+            LauncherError::NoHLExe => Hint::new(
+                "Pick the game executable in your Half-Life folder, ▼ finds it in Steam libraries",
+                Field::HlExe,
+            ),
+            #[cfg(windows)]
+            LauncherError::InjectError { path, .. } => Hint::new(
+                "Make sure no other Half-Life is running. Antivirus software can block injection.",
+                field_of_path(config, path),
+            ),
+            #[cfg(windows)]
+            LauncherError::WindowsAPI { .. } | LauncherError::InjectionFailed { .. } => Hint::new(
+                "Make sure no other Half-Life is running. Antivirus software can block injection.",
+                None,
+            ),
+            LauncherError::IOContext { path, source, .. } => {
+                let text = match source.kind() {
+                    std::io::ErrorKind::NotFound => "Check that the path exists",
+                    std::io::ErrorKind::PermissionDenied => {
+                        "Check that you are allowed to use this path"
+                    }
+                    _ => "Check the path, and that the disk is not full or read-only",
+                };
+
+                Hint::new(text, field_of_path(config, path))
+            }
+            #[cfg(windows)]
+            LauncherError::WindowsAPIContext { path, .. } => Hint::new(
+                "Check the path, and that antivirus software is not blocking it",
+                field_of_path(config, path),
+            ),
+            LauncherError::TomlParsingError { .. } => Hint::new(
+                "The old config was backed up next to the launcher, defaults are used for now",
+                None,
+            ),
+            LauncherError::TomlWritingError { .. } => {
+                Hint::new("Check that the launcher folder is writable", None)
+            }
+            LauncherError::NoGameDirectory { .. } => {
+                Hint::new("Give the full path to the game executable", Field::HlExe)
+            }
+            LauncherError::FileDoesNotExist { path } => Hint::new(
+                "Check the path or pick the file again with +",
+                field_of_path(config, path),
+            ),
+            LauncherError::BxtNotLoaded { .. } => Hint::new(
+                "Check that BunnymodXT supports this Half-Life build, the game output says more",
+                Field::Bxt,
+            ),
+            LauncherError::StartupTimeout { .. } => Hint::new(
+                "Give BXT more time, or check that it supports this Half-Life build",
+                Field::StartupTimeout,
+            ),
+            LauncherError::GameExitedEarly { .. } => Hint::new(
+                "Try without BXT and plugins to narrow it down, the crash bundle has the output",
+                None,
+            ),
+            LauncherError::HookFailed { command, .. } => {
+                let field = if config
+                    .pre_launch_hooks
+                    .lines()
+                    .any(|line| line.trim() == command)
+                {
+                    Field::PreLaunchHooks
+                } else {
+                    Field::PostExitHooks
+                };
+
+                Hint::new("Fix the command, or comment it out with #", field)
+            }
+            LauncherError::NotPe { path } => Hint::new(
+                "Pick the Windows build of this file",
+                field_of_path(config, path),
+            ),
+            LauncherError::WrongPeArchitecture { path, .. } => Hint::new(
+                "Pick the 32-bit build of this file",
+                field_of_path(config, path),
+            ),
+            #[cfg(not(windows))]
+            LauncherError::NotElf { path } => Hint::new(
+                "Pick the Linux build of this file, or switch to Wine for Windows files",
+                field_of_path(config, path),
+            ),
+            #[cfg(not(windows))]
+            LauncherError::WrongElfArchitecture { path, .. } => Hint::new(
+                "Pick the 32-bit build of this file",
+                field_of_path(config, path),
+            ),
+            #[cfg(not(windows))]
+            LauncherError::CannotFindSteam => Hint::new(
+                "Looked in ~/.steam, ~/.local/share/Steam and Flatpak Steam. Install Steam or \
+                 pick another runtime.",
+                Field::Runtime,
+            ),
+            #[cfg(not(windows))]
+            LauncherError::CannotFindSniper => Hint::new(
+                "Looked in every Steam library. Install \"Steam Linux Runtime 3.0 (sniper)\" \
+                 from Steam or pick another runtime.",
+                Field::Runtime,
+            ),
+            #[cfg(not(windows))]
+            LauncherError::NoCustomRuntime => {
+                Hint::new("Pick the script that runs the game", Field::CustomRuntime)
+            }
+            #[cfg(not(windows))]
+            LauncherError::NoWine => Hint::new(
+                "Looked for the Wine binary in PATH. Install Wine or give the full path to one.",
+                Field::WineBinary,
+            ),
+            #[cfg(not(windows))]
+            LauncherError::NoWineInjector => Hint::new(
+                "Pick bxt-launcher.exe from a Windows release, it loads BXT inside the prefix. Or \
+                 turn off BXT, bxt-rs and plugins.",
+                Field::WineInjector,
+            ),
+            #[cfg(not(windows))]
+            LauncherError::NoProton => Hint::new(
+                "Pick a Proton folder, ▼ lists the ones Steam has installed",
+                Field::Proton,
+            ),
+            #[cfg(not(windows))]
+            LauncherError::NoProtonCompatData => Hint::new(
+                "Pick a folder for the Proton prefix",
+                Field::ProtonCompatData,
+            ),
+            #[cfg(not(windows))]
+            LauncherError::CannotFocusGame { .. } => {
+                Hint::new("Install xdotool, or switch to the game yourself", None)
+            }
+            #[cfg(windows)]
+            LauncherError::CannotFocusGame { .. } => Hint::new("Switch to the game yourself", None),
+            LauncherError::InvalidInjectorArguments { .. } => Hint::new(
+                "This is how the launcher runs itself inside Wine, start it without arguments",
+                None,
+            ),
+            LauncherError::InvalidCpuList { .. } => Hint::new(
+                "Use CPU numbers and ranges separated by commas, like 0-3,6",
+                Field::CpuAffinity,
+            ),
+            #[cfg(not(windows))]
+            LauncherError::SchedulingFailed { .. } => Hint::new(
+                "Priority below 0 needs root or CAP_SYS_NICE, and the listed CPUs must exist",
+                Field::Priority,
+            ),
+        }
+    }
+}
+
+/// The setting holding `path`, trimmed like the launch saw it.
+fn field_of_path(config: &Config, path: &Path) -> Option<Field> {
+    let is = |value: &Path| trim_path(value) == path;
+
+    if is(&config.hlexe) {
+        return Some(Field::HlExe);
+    }
+
+    if is(&config.bxt) {
+        return Some(Field::Bxt);
+    }
+
+    if is(&config.bxt_rs) {
+        return Some(Field::BxtRs);
+    }
+
+    if let Some(index) = config.plugins.iter().position(|plugin| is(&plugin.path)) {
+        return Some(Field::Plugin(index));
+    }
+
+    #[cfg(not(windows))]
+    {
+        if is(&config.wine_binary) {
+            return Some(Field::WineBinary);
+        }
+
+        if is(&config.wine_injector) {
+            return Some(Field::WineInjector);
+        }
+
+        if is(&config.custom_runtime) {
+            return Some(Field::CustomRuntime);
+        }
+
+        // the proton script is looked for inside the configured folder
+        if path.parent().is_some_and(|parent| {
+            !config.proton.as_os_str().is_empty() && trim_path(&config.proton) == parent
+        }) {
+            return Some(Field::Proton);
+        }
+    }
+
+    None
+}
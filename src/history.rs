@@ -0,0 +1,173 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Config, launcher_dir},
+    error::{ErrorContext, LauncherError},
+    launch::LaunchPlan,
+    session::{ExitStatus, SessionInfo},
+    sha256,
+};
+
+const HISTORY_FILE_NAME: &str = "bxt_launcher_history.toml";
+
+/// Sessions finishing at the same time would otherwise overwrite each other's entries
+static HISTORY_FILE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Every launch that got the game running, oldest first.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct History {
+    #[serde(default)]
+    pub launches: Vec<LaunchRecord>,
+}
+
+/// Everything needed to tell what ran and to run it again.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LaunchRecord {
+    /// One-based, as shown in the UI
+    pub profile: usize,
+    /// Seconds since the Unix epoch
+    pub started: u64,
+    pub ended: Option<u64>,
+    pub exit_code: Option<i32>,
+    pub exit_signal: Option<i32>,
+    pub libraries: Vec<LibraryHash>,
+    pub plan: LaunchPlan,
+    pub config: Config,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryHash {
+    #[serde(with = "crate::os_serde")]
+    pub path: PathBuf,
+    pub sha256: String,
+}
+
+/// Total playtime and last launch of one profile.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProfileStats {
+    pub playtime: Duration,
+    pub last_used: Option<u64>,
+}
+
+impl History {
+    pub fn load() -> Result<Self, LauncherError> {
+        let path = launcher_dir().join(HISTORY_FILE_NAME);
+
+        match fs::read_to_string(&path) {
+            Ok(buffer) => Ok(toml::from_str(&buffer)?),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err).context("read history file", &path),
+        }
+    }
+
+    /// Adds a launch to the history file.
+    pub fn append(record: LaunchRecord) -> Result<(), LauncherError> {
+        let _lock = HISTORY_FILE_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+
+        let mut history = Self::load()?;
+        history.launches.push(record);
+
+        let path = launcher_dir().join(HISTORY_FILE_NAME);
+
+        fs::write(&path, toml::to_string(&history)?).context("write history file", &path)?;
+
+        Ok(())
+    }
+
+    pub fn profile_stats(&self, profile: usize) -> ProfileStats {
+        self.launches
+            .iter()
+            .filter(|record| record.profile == profile)
+            .fold(ProfileStats::default(), |stats, record| ProfileStats {
+                playtime: stats.playtime + record.duration().unwrap_or_default(),
+                last_used: stats.last_used.max(Some(record.started)),
+            })
+    }
+}
+
+impl LaunchRecord {
+    pub fn new(session: &SessionInfo, config: &Config, plan: &LaunchPlan) -> Self {
+        Self {
+            profile: session.profile,
+            started: unix_time(session.started),
+            ended: session.ended.map(unix_time),
+            exit_code: session.exit_status.and_then(|status| status.code),
+            exit_signal: session.exit_status.and_then(|status| status.signal),
+            libraries: hash_libraries(config),
+            plan: plan.clone(),
+            config: config.clone(),
+        }
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs(
+            self.ended?.saturating_sub(self.started),
+        ))
+    }
+
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        (self.exit_code.is_some() || self.exit_signal.is_some()).then_some(ExitStatus {
+            code: self.exit_code,
+            signal: self.exit_signal,
+        })
+    }
+
+    /// Libraries that are gone or no longer match what was loaded back then.
+    pub fn changed_libraries(&self) -> Vec<&Path> {
+        self.libraries
+            .iter()
+            .filter(|library| {
+                sha256::hash_file(&library.path).ok().as_ref() != Some(&library.sha256)
+            })
+            .map(|library| library.path.as_path())
+            .collect()
+    }
+}
+
+fn hash_libraries(config: &Config) -> Vec<LibraryHash> {
+    config
+        .libraries()
+        .into_iter()
+        .filter_map(|path| {
+            Some(LibraryHash {
+                path: path.to_path_buf(),
+                sha256: sha256::hash_file(path).ok()?,
+            })
+        })
+        .collect()
+}
+
+fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// UTC date and time for display.
+pub fn format_time(unix_time: u64) -> String {
+    match jiff::Timestamp::from_second(unix_time as i64) {
+        Ok(timestamp) => timestamp.strftime("%Y-%m-%d %H:%M UTC").to_string(),
+        Err(_) => unix_time.to_string(),
+    }
+}
+
+/// Hours and minutes, or minutes and seconds for short sessions.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+
+    if seconds >= 3600 {
+        format!("{}h {}m", seconds / 3600, seconds % 3600 / 60)
+    } else {
+        format!("{}m {}s", seconds / 60, seconds % 60)
+    }
+}
@@ -0,0 +1,82 @@
+use std::process::Command;
+
+use crate::{
+    config::Config,
+    error::{ErrorContext, LauncherError},
+    session::SessionInfo,
+};
+
+/// Runs every pre-launch hook in order, stopping at the first one that fails. Also returns how
+/// many succeeded, whatever those changed is for the post-exit hooks to undo.
+pub fn run_pre_launch(
+    config: &Config,
+    session: &SessionInfo,
+) -> (usize, Result<(), LauncherError>) {
+    let mut succeeded = 0;
+
+    for command in hook_lines(&config.pre_launch_hooks) {
+        if let Err(err) = run_hook(config, session, command) {
+            return (succeeded, Err(err));
+        }
+
+        succeeded += 1;
+    }
+
+    (succeeded, Ok(()))
+}
+
+/// Runs every post-exit hook, including the ones after a failing hook.
+pub fn run_post_exit(config: &Config, session: &SessionInfo) -> Vec<LauncherError> {
+    hook_lines(&config.post_exit_hooks)
+        .filter_map(|command| run_hook(config, session, command).err())
+        .collect()
+}
+
+/// One hook per line, blank lines and `#` comments are skipped.
+fn hook_lines(hooks: &str) -> impl Iterator<Item = &str> {
+    hooks
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+fn run_hook(config: &Config, session: &SessionInfo, command: &str) -> Result<(), LauncherError> {
+    let mut cmd = shell_command(command);
+
+    cmd.envs(session.env());
+
+    // so hooks can refer to game files relatively
+    if let Ok(hl_root) = config.hl_root()
+        && hl_root.is_dir()
+    {
+        cmd.current_dir(hl_root);
+    }
+
+    let status = cmd.status().context("run hook", command)?;
+
+    if !status.success() {
+        return Err(LauncherError::HookFailed {
+            command: command.to_owned(),
+            status: crate::session::ExitStatus::from(status).to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command);
+    cmd
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+
+    // cmd.exe does its own parsing of everything after /C
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/C").raw_arg(command);
+    cmd
+}
@@ -0,0 +1,609 @@
+//! Order and timing of loading bxt-rs and BunnymodXT into a suspended game.
+//!
+//! Only Windows injects this way, but the sequencing is plain code over [`InjectionBackend`], so
+//! it builds everywhere and can be driven by a fake backend. Under Wine and Proton the Windows
+//! build does the injecting, started by the Linux one with [`injector_args`].
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    config::{Config, Plugin},
+    error::LauncherError,
+    launch::LaunchPlan,
+    session::ExitStatus,
+};
+
+/// First argument that starts the launcher as an injector instead of the UI
+pub const INJECT_FLAG: &str = "--inject";
+
+/// What happened while waiting for an injected DLL to say it is done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Readiness {
+    Ready,
+    Exited(ExitStatus),
+    TimedOut,
+}
+
+/// Platform side of injection: one game process, started suspended.
+pub trait InjectionBackend {
+    /// Can fail after the process is created, [`InjectionBackend::terminate`] cleans that up.
+    fn spawn_suspended(&mut self, plan: &LaunchPlan) -> Result<(), LauncherError>;
+
+    fn inject(&mut self, dll: &Path) -> Result<(), LauncherError>;
+
+    /// Waits for the last injected DLL to signal it has loaded, `None` waits forever. The
+    /// signal is reset before returning so the next DLL can use it.
+    fn wait_ready(&mut self, timeout: Option<Duration>) -> Result<Readiness, LauncherError>;
+
+    fn resume(&mut self) -> Result<(), LauncherError>;
+
+    /// Does nothing when there is no process.
+    fn terminate(&mut self) -> Result<(), LauncherError>;
+}
+
+/// A DLL to inject and whether to wait for it to signal it has loaded.
+#[derive(Debug, Clone, Copy)]
+pub struct Injection<'a> {
+    pub path: &'a Path,
+    /// Only BunnymodXT and bxt-rs know to fire the event
+    pub signals_ready: bool,
+}
+
+/// bxt-rs always goes before BunnymodXT, plugins follow in the order they are listed.
+pub fn injection_order(config: &Config) -> Vec<Injection<'_>> {
+    let mut dlls = vec![];
+
+    if config.enable_bxt_rs {
+        dlls.push(Injection {
+            path: &config.bxt_rs,
+            signals_ready: true,
+        });
+    }
+
+    if config.enable_bxt {
+        dlls.push(Injection {
+            path: &config.bxt,
+            signals_ready: true,
+        });
+    }
+
+    dlls.extend(config.enabled_plugins().map(|path| Injection {
+        path,
+        signals_ready: false,
+    }));
+
+    dlls
+}
+
+/// Arguments that make the launcher's Windows build an injector, for Wine and Proton where
+/// nothing can be loaded into a Windows game from Linux:
+///
+/// ```text
+/// bxt-launcher.exe --inject [--bxt-rs <dll>] [--bxt <dll>] [--plugin <dll>]... -- <hl.exe> <game arguments>
+/// ```
+///
+/// The game and its arguments are for the caller to add after these. `path` turns each DLL path
+/// into one the Windows side can open.
+pub fn injector_args(config: &Config, path: impl Fn(&Path) -> OsString) -> Vec<OsString> {
+    let mut args = vec![OsString::from(INJECT_FLAG)];
+
+    if config.enable_bxt_rs {
+        args.extend(["--bxt-rs".into(), path(&config.bxt_rs)]);
+    }
+
+    if config.enable_bxt {
+        args.extend(["--bxt".into(), path(&config.bxt)]);
+    }
+
+    for plugin in config.enabled_plugins() {
+        args.extend(["--plugin".into(), path(plugin)]);
+    }
+
+    args.push("--".into());
+    args
+}
+
+/// Reads [`injector_args`] back, `None` if they do not start with [`INJECT_FLAG`].
+///
+/// The config only has the libraries set and waits for them forever, whoever started the
+/// injector keeps time and kills it along with the game.
+pub fn parse_injector_args(
+    args: impl IntoIterator<Item = OsString>,
+) -> Option<Result<(Config, LaunchPlan), LauncherError>> {
+    let mut args = args.into_iter();
+
+    if args.next()? != INJECT_FLAG {
+        return None;
+    }
+
+    Some(parse_injector_options(args))
+}
+
+fn parse_injector_options(
+    mut args: impl Iterator<Item = OsString>,
+) -> Result<(Config, LaunchPlan), LauncherError> {
+    let invalid = |reason: String| LauncherError::InvalidInjectorArguments { reason };
+
+    let mut config = Config {
+        startup_timeout: 0,
+        kill_on_timeout: false,
+        ..Default::default()
+    };
+
+    loop {
+        let Some(option) = args.next() else {
+            return Err(invalid("missing `--` before the game".to_owned()));
+        };
+
+        if option == "--" {
+            break;
+        }
+
+        let path = args
+            .next()
+            .map(PathBuf::from)
+            .ok_or_else(|| invalid(format!("missing path after {}", option.display())))?;
+
+        match option.to_str() {
+            Some("--bxt-rs") => {
+                config.bxt_rs = path;
+                config.enable_bxt_rs = true;
+            }
+            Some("--bxt") => {
+                config.bxt = path;
+                config.enable_bxt = true;
+            }
+            Some("--plugin") => config.plugins.push(Plugin {
+                path,
+                enabled: true,
+            }),
+            _ => return Err(invalid(format!("unknown option {}", option.display()))),
+        }
+    }
+
+    let program = args
+        .next()
+        .ok_or_else(|| invalid("missing the game after `--`".to_owned()))?;
+
+    let mut plan = LaunchPlan::new(program);
+    plan.args(args);
+
+    config.hlexe = plan.program.clone();
+
+    Ok((config, plan))
+}
+
+/// Starts the game, injects every DLL in order waiting for each to load, then resumes the game.
+///
+/// Every DLL gets the whole `timeout` to signal it is ready. Running out of it kills the game if `kill_on_timeout` is set. Otherwise the game is
+/// resumed without the remaining DLLs and the timeout is returned for reporting. Any other
+/// failure kills the game, a suspended process is of no use to anyone.
+pub fn run(
+    backend: &mut impl InjectionBackend,
+    plan: &LaunchPlan,
+    dlls: &[Injection],
+    timeout: Option<Duration>,
+    kill_on_timeout: bool,
+) -> Result<Option<LauncherError>, LauncherError> {
+    let result = spawn_and_inject(backend, plan, dlls, timeout, kill_on_timeout);
+
+    if result.is_err() {
+        // the original error says more than a failure to clean up
+        let _ = backend.terminate();
+    }
+
+    result
+}
+
+fn spawn_and_inject(
+    backend: &mut impl InjectionBackend,
+    plan: &LaunchPlan,
+    dlls: &[Injection],
+    timeout: Option<Duration>,
+    kill_on_timeout: bool,
+) -> Result<Option<LauncherError>, LauncherError> {
+    backend.spawn_suspended(plan)?;
+
+    let load_error = match inject_all(backend, dlls, timeout)? {
+        Some(err) if kill_on_timeout => return Err(err),
+        load_error => load_error,
+    };
+
+    backend.resume()?;
+
+    Ok(load_error)
+}
+
+/// Returns the timeout error if waiting ran out, the game is still suspended then.
+fn inject_all(
+    backend: &mut impl InjectionBackend,
+    dlls: &[Injection],
+    timeout: Option<Duration>,
+) -> Result<Option<LauncherError>, LauncherError> {
+    for dll in dlls {
+        backend.inject(dll.path)?;
+
+        if !dll.signals_ready {
+            continue;
+        }
+
+        match backend.wait_ready(timeout)? {
+            Readiness::Ready => (),
+            Readiness::Exited(status) => {
+                return Err(LauncherError::GameExitedEarly {
+                    status: status.to_string(),
+                });
+            }
+            Readiness::TimedOut => {
+                return Ok(Some(LauncherError::StartupTimeout {
+                    seconds: timeout.unwrap_or_default().as_secs() as u32,
+                    waiting_for: dll.path.display().to_string(),
+                }));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, io, path::PathBuf};
+
+    use super::*;
+    use crate::config::Plugin;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Call {
+        Spawn,
+        Inject(PathBuf),
+        Wait,
+        Resume,
+        Terminate,
+    }
+
+    /// Records what it is asked to do, `Ready` for every wait unless told otherwise.
+    #[derive(Default)]
+    struct FakeBackend {
+        calls: Vec<Call>,
+        spawned: bool,
+        fail_after_spawn: bool,
+        fail_inject: Option<PathBuf>,
+        readiness: VecDeque<Readiness>,
+        waited_for: Vec<Option<Duration>>,
+    }
+
+    impl InjectionBackend for FakeBackend {
+        fn spawn_suspended(&mut self, plan: &LaunchPlan) -> Result<(), LauncherError> {
+            self.calls.push(Call::Spawn);
+            self.spawned = true;
+
+            if self.fail_after_spawn {
+                return Err(LauncherError::IOContext {
+                    operation: "start",
+                    path: plan.program.clone(),
+                    source: io::Error::from(io::ErrorKind::PermissionDenied),
+                });
+            }
+
+            Ok(())
+        }
+
+        fn inject(&mut self, dll: &Path) -> Result<(), LauncherError> {
+            self.calls.push(Call::Inject(dll.to_path_buf()));
+
+            if self.fail_inject.as_deref() == Some(dll) {
+                return Err(LauncherError::FileDoesNotExist { path: dll.into() });
+            }
+
+            Ok(())
+        }
+
+        fn wait_ready(&mut self, timeout: Option<Duration>) -> Result<Readiness, LauncherError> {
+            self.calls.push(Call::Wait);
+            self.waited_for.push(timeout);
+
+            Ok(self.readiness.pop_front().unwrap_or(Readiness::Ready))
+        }
+
+        fn resume(&mut self) -> Result<(), LauncherError> {
+            self.calls.push(Call::Resume);
+
+            Ok(())
+        }
+
+        fn terminate(&mut self) -> Result<(), LauncherError> {
+            if self.spawned {
+                self.calls.push(Call::Terminate);
+                self.spawned = false;
+            }
+
+            Ok(())
+        }
+    }
+
+    fn config() -> Config {
+        Config {
+            bxt: "BunnymodXT.dll".into(),
+            enable_bxt: true,
+            bxt_rs: "bxt_rs.dll".into(),
+            enable_bxt_rs: true,
+            plugins: vec![
+                Plugin {
+                    path: "first.dll".into(),
+                    enabled: true,
+                },
+                Plugin {
+                    path: "off.dll".into(),
+                    enabled: false,
+                },
+                Plugin {
+                    path: "second.dll".into(),
+                    enabled: true,
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn inject(path: &str) -> Call {
+        Call::Inject(path.into())
+    }
+
+    fn run_fake(
+        backend: &mut FakeBackend,
+        config: &Config,
+        timeout: Option<Duration>,
+        kill_on_timeout: bool,
+    ) -> Result<Option<LauncherError>, LauncherError> {
+        run(
+            backend,
+            &LaunchPlan::new("hl.exe"),
+            &injection_order(config),
+            timeout,
+            kill_on_timeout,
+        )
+    }
+
+    #[test]
+    fn injector_args_read_back() {
+        let mut args = injector_args(&config(), |path| path.as_os_str().to_owned());
+        args.extend(["hl.exe".into(), "-game".into(), "my mod".into()]);
+
+        let (parsed, plan) = parse_injector_args(args).unwrap().unwrap();
+
+        assert_eq!(
+            injection_order(&parsed)
+                .iter()
+                .map(|dll| (dll.path, dll.signals_ready))
+                .collect::<Vec<_>>(),
+            injection_order(&config())
+                .iter()
+                .map(|dll| (dll.path, dll.signals_ready))
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(parsed.startup_timeout, 0);
+        assert_eq!(plan.program, Path::new("hl.exe"));
+        assert_eq!(plan.args, ["-game", "my mod"]);
+    }
+
+    #[test]
+    fn injector_args_without_libraries() {
+        let args = ["--inject", "--", "hl.exe"].map(OsString::from);
+
+        let (parsed, plan) = parse_injector_args(args).unwrap().unwrap();
+
+        assert!(parsed.libraries().is_empty());
+        assert_eq!(plan.program, Path::new("hl.exe"));
+        assert!(plan.args.is_empty());
+    }
+
+    #[test]
+    fn not_an_injector() {
+        assert!(parse_injector_args(Vec::<OsString>::new()).is_none());
+        assert!(parse_injector_args(["--bxt".into()]).is_none());
+    }
+
+    #[test]
+    fn bad_injector_args() {
+        for args in [
+            &["--inject"][..],
+            &["--inject", "--bxt"],
+            &["--inject", "--bxt", "a.dll"],
+            &["--inject", "--other", "a.dll", "--", "hl.exe"],
+            &["--inject", "--"],
+        ] {
+            let result = parse_injector_args(args.iter().map(OsString::from));
+
+            assert!(
+                matches!(
+                    result,
+                    Some(Err(LauncherError::InvalidInjectorArguments { .. }))
+                ),
+                "{args:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn injects_bxt_rs_then_bxt_then_plugins() {
+        let mut backend = FakeBackend::default();
+
+        let load_error = run_fake(&mut backend, &config(), None, false).unwrap();
+
+        assert!(load_error.is_none());
+        assert_eq!(
+            backend.calls,
+            [
+                Call::Spawn,
+                inject("bxt_rs.dll"),
+                Call::Wait,
+                inject("BunnymodXT.dll"),
+                Call::Wait,
+                inject("first.dll"),
+                inject("second.dll"),
+                Call::Resume,
+            ]
+        );
+        assert_eq!(backend.waited_for, [None, None]);
+    }
+
+    #[test]
+    fn only_enabled_libraries_are_injected() {
+        let mut config = config();
+        config.enable_bxt_rs = false;
+        config.plugins.clear();
+
+        let mut backend = FakeBackend::default();
+        run_fake(&mut backend, &config, None, false).unwrap();
+
+        assert_eq!(
+            backend.calls,
+            [
+                Call::Spawn,
+                inject("BunnymodXT.dll"),
+                Call::Wait,
+                Call::Resume
+            ]
+        );
+    }
+
+    #[test]
+    fn every_dll_gets_the_whole_timeout() {
+        let timeout = Duration::from_secs(30);
+        let mut backend = FakeBackend::default();
+
+        run_fake(&mut backend, &config(), Some(timeout), false).unwrap();
+
+        let [first, second] = backend.waited_for[..] else {
+            panic!("expected two waits, got {:?}", backend.waited_for);
+        };
+
+        assert_eq!(first, Some(timeout));
+        assert_eq!(second, Some(timeout));
+    }
+
+    #[test]
+    fn timeout_resumes_without_the_rest() {
+        let mut backend = FakeBackend {
+            readiness: [Readiness::TimedOut].into(),
+            ..Default::default()
+        };
+
+        let load_error = run_fake(&mut backend, &config(), Some(Duration::from_secs(5)), false);
+
+        assert!(matches!(
+            load_error,
+            Ok(Some(LauncherError::StartupTimeout { seconds: 5, ref waiting_for }))
+                if waiting_for == "bxt_rs.dll"
+        ));
+        assert_eq!(
+            backend.calls,
+            [Call::Spawn, inject("bxt_rs.dll"), Call::Wait, Call::Resume]
+        );
+    }
+
+    #[test]
+    fn timeout_kills_when_asked_to() {
+        let mut backend = FakeBackend {
+            readiness: [Readiness::Ready, Readiness::TimedOut].into(),
+            ..Default::default()
+        };
+
+        let result = run_fake(&mut backend, &config(), Some(Duration::from_secs(5)), true);
+
+        assert!(matches!(
+            result,
+            Err(LauncherError::StartupTimeout { ref waiting_for, .. })
+                if waiting_for == "BunnymodXT.dll"
+        ));
+        assert_eq!(
+            backend.calls,
+            [
+                Call::Spawn,
+                inject("bxt_rs.dll"),
+                Call::Wait,
+                inject("BunnymodXT.dll"),
+                Call::Wait,
+                Call::Terminate
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_injection_terminates() {
+        let mut backend = FakeBackend {
+            fail_inject: Some("BunnymodXT.dll".into()),
+            ..Default::default()
+        };
+
+        let result = run_fake(&mut backend, &config(), None, false);
+
+        assert!(matches!(
+            result,
+            Err(LauncherError::FileDoesNotExist { .. })
+        ));
+        assert_eq!(
+            backend.calls,
+            [
+                Call::Spawn,
+                inject("bxt_rs.dll"),
+                Call::Wait,
+                inject("BunnymodXT.dll"),
+                Call::Terminate
+            ]
+        );
+    }
+
+    #[test]
+    fn failure_after_spawn_terminates() {
+        let mut backend = FakeBackend {
+            fail_after_spawn: true,
+            ..Default::default()
+        };
+
+        let result = run_fake(&mut backend, &config(), None, false);
+
+        assert!(matches!(
+            result,
+            Err(LauncherError::IOContext {
+                operation: "start",
+                ..
+            })
+        ));
+        assert_eq!(backend.calls, [Call::Spawn, Call::Terminate]);
+    }
+
+    #[test]
+    fn game_exiting_early_is_an_error() {
+        let status = ExitStatus {
+            code: Some(3),
+            signal: None,
+        };
+        let mut backend = FakeBackend {
+            readiness: [Readiness::Exited(status)].into(),
+            ..Default::default()
+        };
+
+        let result = run_fake(&mut backend, &config(), None, false);
+
+        assert!(matches!(
+            result,
+            Err(LauncherError::GameExitedEarly { ref status }) if status == "code 3"
+        ));
+        assert_eq!(
+            backend.calls,
+            [
+                Call::Spawn,
+                inject("bxt_rs.dll"),
+                Call::Wait,
+                Call::Terminate
+            ]
+        );
+    }
+}
@@ -0,0 +1,192 @@
+//! Finding copies of Half-Life that are already running, so a second launch is a choice rather
+//! than an accident.
+
+use crate::error::LauncherError;
+
+/// Process names Half-Life runs as, compared case-insensitively.
+const GAME_PROCESS_NAMES: &[&str] = &["hl_linux", "hl.exe"];
+
+pub struct RunningGame {
+    pub pid: u32,
+    pub name: String,
+}
+
+fn is_game_process(name: &str) -> bool {
+    GAME_PROCESS_NAMES
+        .iter()
+        .any(|game| game.eq_ignore_ascii_case(name))
+}
+
+/// Native games, and under Wine the `hl.exe` process Wine runs for it.
+#[cfg(not(windows))]
+pub fn find_running_games() -> Vec<RunningGame> {
+    use crate::procfs::{command_line, list_processes};
+
+    list_processes()
+        .into_iter()
+        .filter_map(|process| {
+            // Wine keeps the Windows path as the first argument
+            let name = command_line(process.pid)
+                .first()
+                .and_then(|program| program.rsplit(['/', '\\']).next().map(str::to_owned))
+                .filter(|name| is_game_process(name))
+                .or_else(|| is_game_process(&process.comm).then_some(process.comm))?;
+
+            Some(RunningGame {
+                pid: process.pid,
+                name,
+            })
+        })
+        .collect()
+}
+
+#[cfg(not(windows))]
+pub fn kill(pid: u32) -> Result<(), LauncherError> {
+    use crate::error::ErrorContext;
+
+    if unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) } != 0 {
+        return Err(std::io::Error::last_os_error()).context("stop", format!("pid {pid}"));
+    }
+
+    Ok(())
+}
+
+/// There is no portable way to raise another program's window on Linux, so this asks
+/// `xdotool`, which works on X11 and XWayland.
+#[cfg(not(windows))]
+pub fn focus(pid: u32) -> Result<(), LauncherError> {
+    let status = std::process::Command::new("xdotool")
+        .args(["search", "--pid", &pid.to_string(), "windowactivate"])
+        .status()
+        .map_err(|_| LauncherError::CannotFocusGame {
+            reason: "xdotool is not installed".to_owned(),
+        })?;
+
+    if !status.success() {
+        return Err(LauncherError::CannotFocusGame {
+            reason: format!("no window found for pid {pid}"),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(windows)]
+pub fn find_running_games() -> Vec<RunningGame> {
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, PROCESSENTRY32W, Process32FirstW, Process32NextW,
+        TH32CS_SNAPPROCESS,
+    };
+
+    let mut games = vec![];
+
+    unsafe {
+        let Ok(snapshot) = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) else {
+            return games;
+        };
+
+        let mut entry = PROCESSENTRY32W {
+            dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
+
+        let mut next = Process32FirstW(snapshot, &mut entry);
+
+        while next.is_ok() {
+            let length = entry
+                .szExeFile
+                .iter()
+                .position(|&c| c == 0)
+                .unwrap_or(entry.szExeFile.len());
+            let name = String::from_utf16_lossy(&entry.szExeFile[..length]);
+
+            if is_game_process(&name) {
+                games.push(RunningGame {
+                    pid: entry.th32ProcessID,
+                    name,
+                });
+            }
+
+            next = Process32NextW(snapshot, &mut entry);
+        }
+
+        let _ = CloseHandle(snapshot);
+    }
+
+    games
+}
+
+#[cfg(windows)]
+pub fn kill(pid: u32) -> Result<(), LauncherError> {
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Threading::{OpenProcess, PROCESS_TERMINATE, TerminateProcess};
+
+    unsafe {
+        let process = OpenProcess(PROCESS_TERMINATE, false, pid)?;
+        let res = TerminateProcess(process, 1);
+        let _ = CloseHandle(process);
+
+        res?;
+    }
+
+    Ok(())
+}
+
+#[cfg(windows)]
+pub fn focus(pid: u32) -> Result<(), LauncherError> {
+    use windows::Win32::Foundation::{HWND, LPARAM};
+    use windows::Win32::UI::WindowsAndMessaging::{
+        EnumWindows, GetWindowThreadProcessId, IsWindowVisible, SW_RESTORE, SetForegroundWindow,
+        ShowWindow,
+    };
+    use windows::core::BOOL;
+
+    struct Search {
+        pid: u32,
+        window: Option<HWND>,
+    }
+
+    unsafe extern "system" fn find_window(window: HWND, param: LPARAM) -> BOOL {
+        let search = unsafe { &mut *(param.0 as *mut Search) };
+        let mut pid = 0;
+
+        unsafe { GetWindowThreadProcessId(window, Some(&mut pid)) };
+
+        if pid == search.pid && unsafe { IsWindowVisible(window) }.as_bool() {
+            search.window = Some(window);
+            // stop enumerating
+            return BOOL(0);
+        }
+
+        BOOL(1)
+    }
+
+    let mut search = Search { pid, window: None };
+
+    unsafe {
+        // errors when the callback stops early, which is what finding the window does
+        let _ = EnumWindows(
+            Some(find_window),
+            LPARAM(&mut search as *mut Search as isize),
+        );
+    }
+
+    let Some(window) = search.window else {
+        return Err(LauncherError::CannotFocusGame {
+            reason: format!("no window found for pid {pid}"),
+        });
+    };
+
+    unsafe {
+        let _ = ShowWindow(window, SW_RESTORE);
+
+        if !SetForegroundWindow(window).as_bool() {
+            return Err(LauncherError::CannotFocusGame {
+                reason: "Windows refused to switch windows".to_owned(),
+            });
+        }
+    }
+
+    Ok(())
+}
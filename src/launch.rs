@@ -0,0 +1,619 @@
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    fmt,
+    path::PathBuf,
+    process::Command,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Resolved program, arguments and environment for starting the game.
+///
+/// Backends build this from a [`crate::config::Config`] so that what is about to be run can be
+/// inspected before anything is spawned.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LaunchPlan {
+    #[serde(with = "crate::os_serde")]
+    pub program: PathBuf,
+    #[serde(with = "crate::os_serde::vec")]
+    pub args: Vec<OsString>,
+    #[serde(with = "crate::os_serde::env")]
+    pub env: Vec<(String, OsString)>,
+    /// Left out of the file when `None`, so it has to default
+    #[serde(default, with = "crate::os_serde::option")]
+    pub current_dir: Option<PathBuf>,
+    /// Names of the launcher's variables passed on, all of them if `None`. A trailing `*`
+    /// matches by prefix.
+    #[serde(default)]
+    pub env_allowlist: Option<Vec<String>>,
+    /// Set for the game by the program in between, like `/usr/bin/env` inside Sniper. Already in
+    /// `args`, this is only so [`LaunchPlan::environment`] can show them.
+    #[serde(default, with = "crate::os_serde::env")]
+    pub inner_env: Vec<(String, OsString)>,
+}
+
+/// How a variable ends up in the game's environment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvSource {
+    Inherited,
+    Overridden,
+    Removed,
+}
+
+/// One line of [`LaunchPlan::environment`].
+#[derive(Debug, Clone)]
+pub struct EnvVar {
+    pub name: String,
+    pub value: String,
+    pub source: EnvSource,
+}
+
+impl LaunchPlan {
+    pub fn new(program: impl Into<PathBuf>) -> Self {
+        Self {
+            program: program.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn arg(&mut self, arg: impl AsRef<OsStr>) -> &mut Self {
+        self.args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    pub fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args
+            .extend(args.into_iter().map(|arg| arg.as_ref().to_owned()));
+        self
+    }
+
+    pub fn env(&mut self, key: impl Into<String>, value: impl AsRef<OsStr>) -> &mut Self {
+        self.env.push((key.into(), value.as_ref().to_owned()));
+        self
+    }
+
+    pub fn inner_env(&mut self, key: impl Into<String>, value: impl AsRef<OsStr>) -> &mut Self {
+        self.inner_env.push((key.into(), value.as_ref().to_owned()));
+        self
+    }
+
+    pub fn current_dir(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
+        self.current_dir = Some(dir.into());
+        self
+    }
+
+    pub fn env_allowlist<I, S>(&mut self, names: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.env_allowlist = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    fn inherits(&self, name: &str) -> bool {
+        let Some(allowlist) = &self.env_allowlist else {
+            return true;
+        };
+
+        allowlist
+            .iter()
+            .any(|allowed| match allowed.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == allowed,
+            })
+    }
+
+    /// What the game gets compared to the launcher's own environment, sorted by name.
+    pub fn environment(&self) -> Vec<EnvVar> {
+        let mut vars = env::vars_os()
+            .map(|(name, value)| {
+                let name = name.to_string_lossy().into_owned();
+                let source = if self.inherits(&name) {
+                    EnvSource::Inherited
+                } else {
+                    EnvSource::Removed
+                };
+
+                EnvVar {
+                    name,
+                    value: value.to_string_lossy().into_owned(),
+                    source,
+                }
+            })
+            .filter(|var| {
+                !self
+                    .env
+                    .iter()
+                    .chain(&self.inner_env)
+                    .any(|(key, _)| *key == var.name)
+            })
+            .collect::<Vec<_>>();
+
+        // the inner ones are set last, so they win over the plan's own
+        let overridden = self
+            .env
+            .iter()
+            .filter(|(name, _)| !self.inner_env.iter().any(|(key, _)| key == name))
+            .chain(&self.inner_env);
+
+        vars.extend(overridden.map(|(name, value)| EnvVar {
+            name: name.clone(),
+            value: value.to_string_lossy().into_owned(),
+            source: EnvSource::Overridden,
+        }));
+
+        vars.sort_by(|a, b| a.name.cmp(&b.name));
+        vars
+    }
+
+    pub fn to_command(&self) -> Command {
+        let mut cmd = Command::new(&self.program);
+
+        cmd.args(&self.args);
+
+        if self.env_allowlist.is_some() {
+            cmd.env_clear();
+            cmd.envs(env::vars_os().filter(|(name, _)| self.inherits(&name.to_string_lossy())));
+        }
+
+        cmd.envs(self.env.iter().map(|(key, value)| (key, value)));
+
+        if let Some(dir) = &self.current_dir {
+            cmd.current_dir(dir);
+        }
+
+        cmd
+    }
+}
+
+/// Command line as `CreateProcessW` expects it, program included, quoted so that the MSVCRT
+/// parser of the game splits it back into the same arguments.
+///
+/// Plain string work so it is the same on every platform.
+#[cfg_attr(not(windows), allow(dead_code))]
+pub fn windows_command_line(plan: &LaunchPlan) -> String {
+    // argv[0] is parsed without escapes, only quotes, and paths cannot contain quotes anyway
+    let program = plan.program.to_string_lossy();
+    let mut line = if program.contains([' ', '\t']) || program.is_empty() {
+        format!("\"{program}\"")
+    } else {
+        program.into_owned()
+    };
+
+    // arguments come from paths and text typed in, which are valid UTF-16 in practice
+    for arg in &plan.args {
+        line.push(' ');
+        quote_windows_arg(&arg.to_string_lossy(), &mut line);
+    }
+
+    line
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+fn quote_windows_arg(arg: &str, line: &mut String) {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '\n', '\x0b', '"']) {
+        line.push_str(arg);
+        return;
+    }
+
+    line.push('"');
+
+    let mut backslashes = 0;
+
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // every backslash before a quote is escaped, and then the quote itself
+                line.extend(std::iter::repeat_n('\\', backslashes * 2 + 1));
+                line.push('"');
+                backslashes = 0;
+            }
+            c => {
+                // backslashes anywhere else are taken literally
+                line.extend(std::iter::repeat_n('\\', backslashes));
+                line.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+
+    // the closing quote must not be escaped
+    line.extend(std::iter::repeat_n('\\', backslashes * 2));
+    line.push('"');
+}
+
+/// Splits a command line the way the MSVCRT parser does, the reverse of
+/// [`windows_command_line`] for everything after the program. Used for the extra arguments typed
+/// in, so quotes there mean the same on every backend.
+pub fn split_windows_args(line: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut arg = String::new();
+    // `""` is an argument of its own even though it adds nothing
+    let mut started = false;
+    let mut quoted = false;
+    let mut backslashes = 0;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                backslashes += 1;
+                started = true;
+                continue;
+            }
+            '"' => {
+                // pairs of backslashes before a quote are one each, an odd one escapes the quote
+                arg.extend(std::iter::repeat_n('\\', backslashes / 2));
+
+                if backslashes % 2 == 1 {
+                    arg.push('"');
+                } else if quoted && chars.peek() == Some(&'"') {
+                    // a doubled quote inside quotes is a literal one
+                    chars.next();
+                    arg.push('"');
+                } else {
+                    quoted = !quoted;
+                }
+
+                started = true;
+            }
+            ' ' | '\t' if !quoted => {
+                arg.extend(std::iter::repeat_n('\\', backslashes));
+
+                if started {
+                    args.push(std::mem::take(&mut arg));
+                    started = false;
+                }
+            }
+            c => {
+                arg.extend(std::iter::repeat_n('\\', backslashes));
+                arg.push(c);
+                started = true;
+            }
+        }
+
+        backslashes = 0;
+    }
+
+    arg.extend(std::iter::repeat_n('\\', backslashes));
+
+    if started {
+        args.push(arg);
+    }
+
+    args
+}
+
+impl fmt::Display for LaunchPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "program: {}", self.program.display())?;
+        writeln!(f, "args: {:?}", self.args)?;
+
+        if let Some(dir) = &self.current_dir {
+            writeln!(f, "current dir: {}", dir.display())?;
+        }
+
+        match &self.env_allowlist {
+            Some(allowlist) => writeln!(f, "env, only keeping {}:", allowlist.join(" "))?,
+            None => writeln!(f, "env:")?,
+        }
+
+        for (key, value) in &self.env {
+            writeln!(f, "    {key}={}", value.to_string_lossy())?;
+        }
+
+        if !self.inner_env.is_empty() {
+            writeln!(f, "env inside the runtime:")?;
+
+            for (key, value) in &self.inner_env {
+                writeln!(f, "    {key}={}", value.to_string_lossy())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Separated list of paths for variables like `LD_PRELOAD` and `LD_LIBRARY_PATH`.
+///
+/// Entries are kept in the order they are added. Empty entries and ones already in the list are
+/// dropped, so the first occurrence decides the position.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathList {
+    entries: Vec<OsString>,
+}
+
+impl PathList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, entry: impl AsRef<OsStr>) -> &mut Self {
+        let entry = entry.as_ref();
+        let entry = entry
+            .to_str()
+            .map_or(entry, |entry| OsStr::new(entry.trim()));
+
+        if !entry.is_empty() && !self.entries.iter().any(|existing| existing == entry) {
+            self.entries.push(entry.to_owned());
+        }
+
+        self
+    }
+
+    /// Adds every entry of an already joined list, split on any of `separators`.
+    pub fn push_joined(&mut self, list: &str, separators: &[char]) -> &mut Self {
+        for entry in list.split(separators) {
+            self.push(entry);
+        }
+
+        self
+    }
+
+    pub fn join(&self) -> OsString {
+        self.entries.join(OsStr::new(":"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn joined(list: &PathList) -> String {
+        list.join().into_string().unwrap()
+    }
+
+    fn round_trip(plan: &LaunchPlan) -> LaunchPlan {
+        toml::from_str(&toml::to_string(plan).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn plan_without_current_dir_loads() {
+        let mut plan = LaunchPlan::new("hl.exe");
+        plan.arg("-game").arg("valve");
+
+        let loaded = round_trip(&plan);
+
+        assert_eq!(loaded.program, plan.program);
+        assert_eq!(loaded.args, plan.args);
+        assert_eq!(loaded.current_dir, None);
+    }
+
+    #[test]
+    fn inner_env_shows_as_overridden() {
+        let mut plan = LaunchPlan::new("_v2-entry-point");
+        plan.arg("LD_PRELOAD=bxt.so")
+            .inner_env("LD_PRELOAD", "bxt.so")
+            .env_allowlist(["HOME"]);
+
+        let var = plan
+            .environment()
+            .into_iter()
+            .find(|var| var.name == "LD_PRELOAD")
+            .unwrap();
+
+        assert_eq!(var.value, "bxt.so");
+        assert_eq!(var.source, EnvSource::Overridden);
+        assert_eq!(round_trip(&plan).inner_env, plan.inner_env);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn plan_keeps_non_utf8_paths() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let odd = OsStr::from_bytes(b"/games/h\xe4lf-life");
+
+        let mut plan = LaunchPlan::new(odd);
+        plan.arg(odd).env("SOME_PATH", odd).current_dir(odd);
+
+        let loaded = round_trip(&plan);
+
+        assert_eq!(loaded.program, odd);
+        assert_eq!(loaded.args, [odd]);
+        assert_eq!(loaded.env, [("SOME_PATH".to_owned(), odd.to_owned())]);
+        assert_eq!(loaded.current_dir.as_deref(), Some(odd.as_ref()));
+    }
+
+    fn command_line(program: &str, args: &[&str]) -> String {
+        let mut plan = LaunchPlan::new(program);
+        plan.args(args);
+
+        windows_command_line(&plan)
+    }
+
+    #[test]
+    fn command_line_plain_arguments() {
+        assert_eq!(
+            command_line(r"C:\hl\hl.exe", &["-game", "valve", "+map", "c1a0"]),
+            r"C:\hl\hl.exe -game valve +map c1a0"
+        );
+    }
+
+    #[test]
+    fn command_line_program_with_spaces() {
+        assert_eq!(
+            command_line(
+                r"C:\Program Files (x86)\Half-Life\hl.exe",
+                &["-game", "valve"]
+            ),
+            r#""C:\Program Files (x86)\Half-Life\hl.exe" -game valve"#
+        );
+    }
+
+    #[test]
+    fn command_line_arguments_with_spaces() {
+        assert_eq!(
+            command_line("hl.exe", &["-game", "my mod", "a\tb"]),
+            "hl.exe -game \"my mod\" \"a\tb\""
+        );
+    }
+
+    #[test]
+    fn command_line_embedded_quotes() {
+        assert_eq!(
+            command_line("hl.exe", &[r#"say "hi""#, r#"a"b"#]),
+            r#"hl.exe "say \"hi\"" "a\"b""#
+        );
+    }
+
+    #[test]
+    fn command_line_trailing_backslashes() {
+        // only doubled when the closing quote follows them
+        assert_eq!(
+            command_line("hl.exe", &[r"C:\dir\", r"C:\my dir\", r"C:\my dir\\"]),
+            r#"hl.exe C:\dir\ "C:\my dir\\" "C:\my dir\\\\""#
+        );
+    }
+
+    #[test]
+    fn command_line_backslashes_before_quotes() {
+        assert_eq!(
+            command_line("hl.exe", &[r#"a\"b"#, r#"a\\"b"#, r"a\b"]),
+            r#"hl.exe "a\\\"b" "a\\\\\"b" a\b"#
+        );
+    }
+
+    #[test]
+    fn command_line_empty_arguments() {
+        assert_eq!(
+            command_line("hl.exe", &["", "-game", ""]),
+            r#"hl.exe "" -game """#
+        );
+        assert_eq!(command_line("", &[]), r#""""#);
+    }
+
+    #[test]
+    fn split_extras_plain_arguments() {
+        assert_eq!(
+            split_windows_args("  +map c1a0\t-dev  "),
+            ["+map", "c1a0", "-dev"]
+        );
+    }
+
+    #[test]
+    fn split_extras_quoted_argument() {
+        assert_eq!(
+            split_windows_args(r#"+exec "my cfg.cfg" -dev"#),
+            ["+exec", "my cfg.cfg", "-dev"]
+        );
+    }
+
+    #[test]
+    fn split_extras_quotes_inside_argument() {
+        assert_eq!(split_windows_args(r#"+echo a"b c"d"#), ["+echo", "ab cd"]);
+    }
+
+    #[test]
+    fn split_extras_escaped_quotes() {
+        assert_eq!(
+            split_windows_args(r#"\"a\" "b \"c\" d" "e""f""#),
+            [r#""a""#, r#"b "c" d"#, r#"e"f"#]
+        );
+    }
+
+    #[test]
+    fn split_extras_backslashes() {
+        assert_eq!(
+            split_windows_args(r#"C:\dir\ "C:\a b\\" \\\"x"#),
+            [r"C:\dir\", r"C:\a b\", r#"\"x"#]
+        );
+    }
+
+    #[test]
+    fn split_extras_empty_arguments() {
+        assert_eq!(split_windows_args(r#"a "" b"#), ["a", "", "b"]);
+        assert!(split_windows_args("   ").is_empty());
+    }
+
+    #[test]
+    fn split_extras_round_trip() {
+        let args = [
+            "+exec",
+            "my cfg.cfg",
+            r#"say "hi""#,
+            r"C:\a b\",
+            r#"\"x"#,
+            "",
+        ];
+        let line = command_line("hl.exe", &args);
+
+        assert_eq!(split_windows_args(&line), [&["hl.exe"][..], &args].concat());
+    }
+
+    #[test]
+    fn path_list_drops_empty_entries() {
+        let mut list = PathList::new();
+        list.push("").push("  ").push("/a").push("\t");
+
+        assert_eq!(joined(&list), "/a");
+    }
+
+    #[test]
+    fn path_list_keeps_first_of_duplicates() {
+        let mut list = PathList::new();
+        list.push("/a").push("/b").push("/a").push(" /b ");
+
+        assert_eq!(joined(&list), "/a:/b");
+    }
+
+    #[test]
+    fn path_list_trims_entries() {
+        let mut list = PathList::new();
+        list.push("  /a/b c.so \n");
+
+        assert_eq!(joined(&list), "/a/b c.so");
+    }
+
+    #[test]
+    fn path_list_keeps_order() {
+        let mut list = PathList::new();
+        list.push("/c").push("/a").push("/b");
+
+        assert_eq!(joined(&list), "/c:/a:/b");
+    }
+
+    #[test]
+    fn path_list_empty_joins_to_nothing() {
+        assert_eq!(joined(&PathList::new()), "");
+    }
+
+    #[test]
+    fn push_joined_splits_on_colons() {
+        let mut list = PathList::new();
+        list.push("/first").push_joined("/a::/b:/first:", &[':']);
+
+        assert_eq!(joined(&list), "/first:/a:/b");
+    }
+
+    #[test]
+    fn push_joined_splits_on_semicolons() {
+        let mut list = PathList::new();
+        list.push_joined("/a;/b;;/a", &[':', ';']);
+
+        assert_eq!(joined(&list), "/a:/b");
+    }
+
+    #[test]
+    fn push_joined_splits_on_spaces() {
+        let mut list = PathList::new();
+        list.push_joined("/a.so  /b.so:/c.so ", &[':', ' ']);
+
+        assert_eq!(joined(&list), "/a.so:/b.so:/c.so");
+    }
+
+    #[test]
+    fn push_joined_without_separator_in_list() {
+        let mut list = PathList::new();
+        list.push_joined("/only", &[':']);
+
+        assert_eq!(joined(&list), "/only");
+    }
+}
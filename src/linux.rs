@@ -1,71 +1,338 @@
-use std::{env, path::PathBuf};
+use std::{
+    env,
+    ffi::{CString, OsString},
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    process::{Child, Stdio},
+    time::{Duration, Instant},
+};
 
-use crate::{config::Config, error::LauncherError};
+use crate::{
+    backend::GameLauncher,
+    config::{Config, SteamRuntime},
+    error::{ErrorContext, LauncherError},
+    launch::{LaunchPlan, PathList, split_windows_args},
+    procfs::{mapped_files, process_tree},
+    scheduling,
+    session::{ExitStatus, OutputLog},
+    steam::{expand_home, get_library_folders},
+};
 
-pub fn run_bxt(config: &Config) -> Result<(), LauncherError> {
-    use std::{env, path::Path, process::Command};
+const LOAD_POLL_INTERVAL: Duration = Duration::from_millis(250);
 
-    use crate::linux::get_steam_run;
+pub struct GameProcess {
+    child: Child,
+    plan: LaunchPlan,
+    output: OutputLog,
+}
 
-    let config = config.trim();
+impl GameProcess {
+    pub fn pid(&self) -> u32 {
+        self.child.id()
+    }
 
-    config.validate()?;
+    pub fn plan(&self) -> &LaunchPlan {
+        &self.plan
+    }
 
-    let Config {
-        hlexe,
-        bxt,
-        bxt_rs,
-        gamemod,
-        extras,
-        enable_bxt,
-        enable_bxt_rs,
-        use_wine,
-    } = config;
+    pub fn output(&self) -> &OutputLog {
+        &self.output
+    }
 
-    let Some(steam_run_path) = get_steam_run() else {
-        return Err(LauncherError::CannotFindSteam);
-    };
+    pub fn wait(&mut self) -> Result<ExitStatus, LauncherError> {
+        let status = self.child.wait().context("wait for", &self.plan.program)?;
+
+        Ok(status.into())
+    }
+
+    /// Kills the runtime wrapper and everything it started, deepest first.
+    pub fn kill(&mut self) -> Result<(), LauncherError> {
+        for pid in process_tree(self.pid()).into_iter().skip(1).rev() {
+            unsafe { libc::kill(pid as libc::pid_t, libc::SIGKILL) };
+        }
+
+        self.child.kill().context("kill", &self.plan.program)?;
+
+        Ok(())
+    }
+
+    /// Watches until BunnymodXT and bxt-rs are mapped into the game, or something says they
+    /// won't be, for at most `startup_timeout` seconds. Returns `false` if nothing was meant to
+    /// be loaded.
+    ///
+    /// The process spawned is usually a runtime wrapper, so every process below it is looked at.
+    /// This also covers Wine, which maps DLLs as regular files.
+    pub fn wait_for_bxt(&mut self, config: &Config) -> Result<bool, LauncherError> {
+        let config = config.trim();
+
+        let libraries = config
+            .libraries()
+            .into_iter()
+            .filter_map(|path| path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+
+        if libraries.is_empty() {
+            return Ok(false);
+        }
+
+        let timeout = config.startup_timeout();
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            // ld.so says so when it cannot load something from LD_PRELOAD. Runtime wrappers are
+            // 64-bit and print that for every 32-bit library they inherit, which only matters to
+            // the game, and the game is checked to be 32-bit
+            if let Some(line) = self.output.find(|line| {
+                line.contains("cannot be preloaded")
+                    && !line.contains("wrong ELF class")
+                    && libraries.iter().any(|library| line.contains(library))
+            }) {
+                return Err(LauncherError::BxtNotLoaded { reason: line });
+            }
+
+            if let Some(status) = self
+                .child
+                .try_wait()
+                .context("check on", &self.plan.program)?
+            {
+                return Err(LauncherError::GameExitedEarly {
+                    status: ExitStatus::from(status).to_string(),
+                });
+            }
+
+            let mapped = process_tree(self.pid())
+                .into_iter()
+                .flat_map(mapped_files)
+                .collect::<Vec<_>>();
+
+            let missing = libraries
+                .iter()
+                .filter(|library| !mapped.iter().any(|path| path.ends_with(library.as_str())))
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+
+            if missing.is_empty() {
+                return Ok(true);
+            }
+
+            if let Some(deadline) = deadline
+                && Instant::now() >= deadline
+            {
+                return Err(LauncherError::StartupTimeout {
+                    seconds: config.startup_timeout,
+                    waiting_for: missing.join(", "),
+                });
+            }
+
+            std::thread::sleep(LOAD_POLL_INTERVAL);
+        }
+    }
+}
+
+/// `hl_linux` started directly.
+pub struct NativeLinux;
+
+/// `hl_linux` inside one of the Steam runtimes, or a script standing in for one.
+pub struct SteamRuntimeLauncher(pub SteamRuntime);
+
+impl GameLauncher for NativeLinux {
+    fn name(&self) -> &'static str {
+        "native Linux"
+    }
+
+    fn uses_windows_files(&self) -> bool {
+        false
+    }
+
+    fn build_plan(&self, config: &Config) -> Result<LaunchPlan, LauncherError> {
+        let mut plan = LaunchPlan::new(&config.hlexe);
+
+        add_game_args(config, &mut plan)?;
+        add_game_env(config, &mut plan)?;
+
+        Ok(plan)
+    }
+
+    fn spawn(&self, config: &Config, plan: LaunchPlan) -> Result<GameProcess, LauncherError> {
+        spawn(config, plan)
+    }
+}
 
-    if use_wine && !Path::new("/usr/bin/wine").exists() {
-        return Err(LauncherError::NoWine);
+impl GameLauncher for SteamRuntimeLauncher {
+    fn name(&self) -> &'static str {
+        self.0.label()
     }
 
-    let mut cmd = Command::new(steam_run_path);
+    fn uses_windows_files(&self) -> bool {
+        false
+    }
+
+    fn build_plan(&self, config: &Config) -> Result<LaunchPlan, LauncherError> {
+        let mut plan = match self.0 {
+            SteamRuntime::Scout => {
+                let Some(steam_run_path) = get_steam_run() else {
+                    return Err(LauncherError::CannotFindSteam);
+                };
+
+                let mut plan = LaunchPlan::new(steam_run_path);
+                plan.arg(&config.hlexe);
+                plan
+            }
+            SteamRuntime::Sniper => {
+                let Some(entry_point) = get_sniper_entry_point() else {
+                    return Err(LauncherError::CannotFindSniper);
+                };
+
+                // pressure-vessel sets up its own environment inside the container,
+                // so variables for the game have to be passed along explicitly
+                let mut plan = LaunchPlan::new(entry_point);
+                plan.args(["--verb=waitforexitandrun", "--", "/usr/bin/env"]);
+                for (key, value) in game_env(config)? {
+                    let mut arg = OsString::from(format!("{key}="));
+                    arg.push(&value);
+                    plan.arg(arg).inner_env(key, value);
+                }
+                plan.arg(&config.hlexe);
+                plan
+            }
+            SteamRuntime::Custom => {
+                if config.custom_runtime.as_os_str().is_empty() {
+                    return Err(LauncherError::NoCustomRuntime);
+                }
+
+                if !config.custom_runtime.is_file() {
+                    return Err(LauncherError::FileDoesNotExist {
+                        path: config.custom_runtime.clone(),
+                    });
+                }
 
-    // must have hl
-    cmd.arg(&hlexe);
+                let mut plan = LaunchPlan::new(&config.custom_runtime);
+                plan.arg(&config.hlexe);
+                plan
+            }
+            SteamRuntime::Direct => LaunchPlan::new(&config.hlexe),
+        };
 
-    if !gamemod.is_empty() {
-        cmd.arg("-game").arg(gamemod);
+        add_game_args(config, &mut plan)?;
+
+        // Sniper already has them on the command line, the entry point itself is 64-bit and
+        // must not get 32-bit libraries preloaded into it
+        if self.0 != SteamRuntime::Sniper {
+            add_game_env(config, &mut plan)?;
+        }
+
+        Ok(plan)
     }
 
-    if !extras.is_empty() {
-        cmd.arg(extras);
+    fn spawn(&self, config: &Config, plan: LaunchPlan) -> Result<GameProcess, LauncherError> {
+        spawn(config, plan)
     }
+}
+
+/// Runs a plan with the game output captured, for every backend that runs on Linux.
+pub fn spawn(config: &Config, plan: LaunchPlan) -> Result<GameProcess, LauncherError> {
+    let mut cmd = plan.to_command();
+
+    // a file and not a pipe, the game must not depend on the launcher to keep running
+    let (output, file) = OutputLog::create()?;
+    let stderr = file
+        .try_clone()
+        .context("open", output.path().unwrap_or(&plan.program))?;
+
+    cmd.stdout(Stdio::from(file)).stderr(Stdio::from(stderr));
+
+    scheduling::apply(config, &mut cmd)?;
+
+    let child = cmd.spawn().map_err(|source| {
+        // errors from the scheduling calls come back from spawn looking like any other, exec
+        // fails with the same codes on a program that cannot be run
+        let denied = matches!(
+            source.raw_os_error(),
+            Some(libc::EPERM | libc::EACCES | libc::EINVAL)
+        );
+
+        if denied && scheduling::is_configured(config) && is_executable(&plan.program) {
+            LauncherError::SchedulingFailed { source }
+        } else {
+            LauncherError::IOContext {
+                operation: "start",
+                path: plan.program.clone(),
+                source,
+            }
+        }
+    })?;
+
+    Ok(GameProcess {
+        child,
+        plan,
+        output,
+    })
+}
+
+/// Whether exec gets past the permission checks for `path`.
+fn is_executable(path: &Path) -> bool {
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
 
-    let hl_root = Path::new(&hlexe).parent().unwrap();
+    unsafe { libc::access(path.as_ptr(), libc::X_OK) == 0 }
+}
+
+/// `LD_PRELOAD` and `LD_LIBRARY_PATH` for loading BXT and plugins into `hl_linux`.
+fn game_env(config: &Config) -> Result<Vec<(&'static str, OsString)>, LauncherError> {
+    let hl_root = config.hl_root()?;
+
+    // inherited entries go first like a wrapper script would do, bxt-rs always before BunnymodXT
+    let mut library_path = PathList::new();
+    let mut preload = PathList::new();
 
-    let library_path = env::var("LD_LIBRARY_PATH").unwrap_or("".to_owned());
-    let library_path = format!("{}:{}", hl_root.display(), library_path);
+    library_path.push(hl_root);
+
+    // a clean environment would not have them either
+    if !config.strip_inherited_paths && !config.clean_environment {
+        if let Ok(inherited) = env::var("LD_LIBRARY_PATH") {
+            library_path.push_joined(&inherited, &[':', ';']);
+        }
 
-    let mut preload = env::var("LD_PRELOAD").unwrap_or("".to_owned());
+        // ld.so accepts both spaces and colons here
+        if let Ok(inherited) = env::var("LD_PRELOAD") {
+            preload.push_joined(&inherited, &[':', ' ']);
+        }
+    }
 
-    if enable_bxt_rs {
-        preload = format!("{}:{}", preload, bxt_rs);
+    for library in config.libraries() {
+        preload.push(library);
     }
 
-    if enable_bxt {
-        preload = format!("{}:{}", preload, bxt);
+    Ok(vec![
+        ("LD_PRELOAD", preload.join()),
+        ("LD_LIBRARY_PATH", library_path.join()),
+        ("SteamEnv", "1".into()),
+    ])
+}
+
+/// Game arguments and working directory, after whatever runs `hl_linux`.
+fn add_game_args(config: &Config, plan: &mut LaunchPlan) -> Result<(), LauncherError> {
+    let hl_root = config.hl_root()?;
+
+    if !config.gamemod.is_empty() {
+        plan.arg("-game").arg(&config.gamemod);
     }
 
-    cmd.env("LD_PRELOAD", preload);
-    cmd.env("LD_LIBRARY_PATH", library_path);
-    cmd.env("SteamEnv", "1");
+    plan.args(split_windows_args(&config.extras));
 
     // must change to hl root for things to work, apparently
-    cmd.current_dir(hl_root);
+    plan.current_dir(hl_root);
 
-    let _ = cmd.spawn();
+    Ok(())
+}
+
+/// [`game_env`] for the process being spawned, which passes it on to `hl_linux`.
+fn add_game_env(config: &Config, plan: &mut LaunchPlan) -> Result<(), LauncherError> {
+    for (key, value) in game_env(config)? {
+        plan.env(key, value);
+    }
 
     Ok(())
 }
@@ -76,17 +343,19 @@ const PATHS_TO_CHECK: &[&str] = &[
     "~/.local/share/Steam/ubuntu12_32/steam-runtime/run.sh",
 ];
 
-pub fn get_steam_run() -> Option<PathBuf> {
-    let home = env::var("HOME").ok()?;
-
-    for path_str in PATHS_TO_CHECK {
-        let path_str = path_str.strip_prefix("~/").unwrap_or(*path_str);
-        let path = PathBuf::from(&home).join(path_str);
+const SNIPER_ENTRY_POINT: &str = "steamapps/common/SteamLinuxRuntime_sniper/_v2-entry-point";
 
-        if path.exists() {
-            return Some(path);
-        }
-    }
+pub fn get_steam_run() -> Option<PathBuf> {
+    PATHS_TO_CHECK
+        .iter()
+        .filter_map(|path_str| expand_home(path_str))
+        .find(|path| path.exists())
+}
 
-    None
+/// Sniper can be installed to any Steam library, not only the one under the Steam root.
+pub fn get_sniper_entry_point() -> Option<PathBuf> {
+    get_library_folders()
+        .into_iter()
+        .map(|library| library.join(SNIPER_ENTRY_POINT))
+        .find(|path| path.exists())
 }
@@ -1,40 +1,96 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        mpsc::{Receiver, TryRecvError},
+    },
+    time::SystemTime,
+};
 
 use eframe::egui;
-use egui::{include_image, mutex::Mutex};
+use egui::mutex::Mutex;
 
-use crate::{config::ConfigWithProfiles, utils::preview_file_being_dropped};
+use crate::{
+    config::{Config, ConfigWithProfiles, Plugin, WindowState},
+    error::{Field, Hint},
+    history::{History, LaunchRecord},
+    instances::RunningGame,
+    pe::PeInfo,
+    session::SessionEvent,
+    status::{Level, StatusLog},
+    utils::preview_file_being_dropped,
+    validation::{Problems, Severity, Validator},
+};
 
+mod backend;
 mod config;
+mod crash;
+#[cfg(not(windows))]
+mod elf;
 mod error;
+mod history;
+mod hooks;
+mod inject;
+mod instances;
+mod launch;
+mod os_serde;
+mod pe;
+#[cfg(not(windows))]
+mod procfs;
+mod scheduling;
+mod session;
+mod sha256;
+mod status;
+mod steam;
 mod utils;
+mod validation;
+#[cfg(not(windows))]
+mod wine;
 
 #[cfg(not(windows))]
 mod linux;
-#[cfg(not(windows))]
-use crate::linux::run_bxt;
 
 #[cfg(windows)]
 mod windows;
-#[cfg(windows)]
-use crate::windows::run_bxt;
 
 fn main() -> eframe::Result {
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
-    let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([432.0 * ZOOM_FACTOR, 182.0 * ZOOM_FACTOR])
-            .with_resizable(false),
-        ..Default::default()
-    };
+
+    #[cfg(windows)]
+    if let Some(request) = inject::parse_injector_args(std::env::args_os().skip(1)) {
+        let code = request
+            .and_then(|(config, plan)| windows::run_injector(&config, plan))
+            .unwrap_or_else(|err| {
+                eprintln!("{err}");
+                1
+            });
+
+        std::process::exit(code);
+    }
 
     // load config
     let config = Arc::new(Mutex::new(
         ConfigWithProfiles::load_from_default().unwrap_or_default(),
     ));
 
+    let window = config.lock().window;
+
+    let mut viewport = egui::ViewportBuilder::default()
+        .with_inner_size(window.size.unwrap_or(DEFAULT_WINDOW_SIZE))
+        .with_min_inner_size(MIN_WINDOW_SIZE);
+
+    if let Some(position) = window.position {
+        viewport = viewport.with_position(position);
+    }
+
+    let options = eframe::NativeOptions {
+        viewport,
+        ..Default::default()
+    };
+
     let res = eframe::run_native(
         "bxt-launcher",
         options,
@@ -47,23 +103,79 @@ fn main() -> eframe::Result {
     );
 
     // always write when app closes
-    config.lock().write_to_default().unwrap();
+    if let Err(err) = config.lock().write_to_default() {
+        eprintln!("Cannot save config: {err}");
+    }
 
     res
 }
 
 struct BxtLauncher {
     config: Arc<Mutex<ConfigWithProfiles>>,
-    status: String,
+    status: StatusLog,
+    show_log: bool,
+    // zoom is applied once at start, afterwards ctrl +/- changes it
+    zoom_applied: bool,
+    // what to do about the last launch error
+    hint: Option<Hint>,
+    // setting to scroll to and focus on the next frame
+    jump_to: Option<Field>,
+    validator: Validator,
     save_timer: std::time::Instant,
+    // Half-Life installs found in Steam libraries, refreshed whenever the picker is opened
+    hl_installs: Vec<std::path::PathBuf>,
+    #[cfg(not(windows))]
+    proton_installs: Vec<steam::ProtonInstall>,
+    pe_info: PeInfoCache,
+    // launches still running, each one disconnects when done
+    sessions: Vec<Receiver<SessionEvent>>,
+    history: History,
+    show_history: bool,
+    // waiting on what to do about Half-Life already running
+    pending_launch: Option<PendingLaunch>,
+    running_games: Vec<RunningGame>,
+    // environment the current profile would launch with, while it is being shown
+    #[cfg(not(windows))]
+    environment_view: Option<Result<Vec<launch::EnvVar>, String>>,
+}
+
+/// A launch that has been asked for but not started yet.
+struct PendingLaunch {
+    config: Config,
+    /// Exactly what ran before, for relaunches from the history
+    plan: Option<launch::LaunchPlan>,
+    profile_index: usize,
+    status: String,
+}
+
+impl PendingLaunch {
+    fn start(self, ctx: &egui::Context) -> Receiver<SessionEvent> {
+        session::start(self.config, self.plan, self.profile_index, ctx.clone())
+    }
 }
 
 impl BxtLauncher {
     fn new(config: Arc<Mutex<ConfigWithProfiles>>) -> Self {
         Self {
             config,
-            status: String::from("Idle"),
+            status: StatusLog::default(),
+            show_log: false,
+            zoom_applied: false,
+            hint: None,
+            jump_to: None,
+            validator: Validator::default(),
             save_timer: std::time::Instant::now(),
+            hl_installs: vec![],
+            #[cfg(not(windows))]
+            proton_installs: vec![],
+            pe_info: PeInfoCache::new(),
+            sessions: vec![],
+            history: History::load().unwrap_or_default(),
+            show_history: false,
+            pending_launch: None,
+            running_games: vec![],
+            #[cfg(not(windows))]
+            environment_view: None,
         }
     }
 }
@@ -77,12 +189,31 @@ const BXT_RS_FILE_NAME_LINUX: &str = "libbxt_rs.so";
 const HL_EXE_FILE_NAME_WINDOWS: &str = "hl.exe";
 const HL_EXE_FILE_NAME_LINUX: &str = "hl_linux";
 
+#[cfg(not(windows))]
+const PRIORITY_HINT: &str =
+    "Nice level of the game, lower runs first. Below 0 needs root or CAP_SYS_NICE.";
+#[cfg(windows)]
+const PRIORITY_HINT: &str =
+    "Lower runs first. Below 0 is above normal priority, -15 and below is high.";
+
+/// Overall scale the launcher is laid out for, monitor scale included
 const ZOOM_FACTOR: f32 = 1.50;
+const DEFAULT_WINDOW_SIZE: [f32; 2] = [432.0 * ZOOM_FACTOR, 182.0 * ZOOM_FACTOR];
+const MIN_WINDOW_SIZE: [f32; 2] = [320.0, 160.0];
 const SAVE_PERIOD: f32 = 30.;
 
 impl eframe::App for BxtLauncher {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        ctx.set_zoom_factor(ZOOM_FACTOR);
+        if !self.zoom_applied {
+            let zoom = self.config.lock().window.zoom.unwrap_or_else(|| {
+                // HiDPI monitors are scaled already, only make up for what they lack
+                let native = ctx.native_pixels_per_point().unwrap_or(1.);
+                (ZOOM_FACTOR / native).clamp(1., ZOOM_FACTOR)
+            });
+
+            ctx.set_zoom_factor(zoom);
+            self.zoom_applied = true;
+        }
 
         // occasionally save the file so people don't forget
         {
@@ -92,210 +223,161 @@ impl eframe::App for BxtLauncher {
                     Ok(_) => {
                         self.save_timer = now;
                     }
-                    Err(err) => self.status = err.to_string(),
+                    Err(err) => self.status.error(err.to_string()),
                 }
             }
         }
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            let mut should_save_file = false;
+        // pick up whatever running launches have to say
+        let session_count = self.sessions.len();
+        self.sessions.retain(|session| {
+            loop {
+                match session.try_recv() {
+                    Ok(event) => {
+                        self.status.push(event.level(), event.to_string());
 
-            let mut configs = self.config.lock();
-            let profle_count = configs.configs.len();
-            let current_profile_index = configs.current_profile;
+                        if let SessionEvent::Error(_, hint) = event {
+                            self.hint = Some(hint);
+                        }
+                    }
+                    Err(TryRecvError::Empty) => break true,
+                    Err(TryRecvError::Disconnected) => break false,
+                }
+            }
+        });
 
-            let current_profile = &mut configs.configs[current_profile_index];
+        // finished launches have been added to the history file
+        if self.sessions.len() != session_count {
+            match History::load() {
+                Ok(history) => self.history = history,
+                Err(err) => self.status.error(err.to_string()),
+            }
+        }
 
-            #[cfg(not(windows))]
-            let use_windows_files = current_profile.use_wine;
+        let mut relaunch = None;
 
-            #[cfg(windows)]
-            let use_windows_files = true;
+        egui::Window::new("History")
+            .open(&mut self.show_history)
+            .default_size([400., 200.])
+            .show(ctx, |ui| {
+                history_list(ui, &self.history, &mut relaunch);
+            });
 
-            let hl_exe_file_name = if cfg!(windows) || use_windows_files {
-                HL_EXE_FILE_NAME_WINDOWS
-            } else {
-                HL_EXE_FILE_NAME_LINUX
-            };
+        #[cfg(not(windows))]
+        if self.environment_view.is_some() {
+            let mut open = true;
 
-            let bxt_file_name = if cfg!(windows) || use_windows_files {
-                BXT_FILE_NAME_WINDOWS
-            } else {
-                BXT_FILE_NAME_LINUX
-            };
+            egui::Window::new("Environment")
+                .open(&mut open)
+                .default_size([400., 300.])
+                .show(ctx, |ui| {
+                    if let Some(view) = &self.environment_view {
+                        environment_list(ui, view);
+                    }
+                });
 
-            let bxt_rs_file_name = if cfg!(windows) || use_windows_files {
-                BXT_RS_FILE_NAME_WINDOWS
-            } else {
-                BXT_RS_FILE_NAME_LINUX
-            };
+            if !open {
+                self.environment_view = None;
+            }
+        }
 
-            egui::Grid::new("ui grid")
-                .num_columns(4)
-                .max_col_width(260. * ZOOM_FACTOR)
-                .min_col_width(8.)
-                .show(ui, |ui| {
-                    ui.label(hl_exe_file_name);
-                    if ui
-                        .add(
-                            egui::TextEdit::singleline(&mut current_profile.hlexe)
-                                .hint_text(format!("Drag-and-drop {}", hl_exe_file_name)),
-                        )
-                        .lost_focus()
-                    {
-                        should_save_file = true;
-                    }
+        // asked to launch while another Half-Life is running
+        if self.pending_launch.is_some() {
+            let mut open = true;
+            let mut launch_anyway = false;
 
-                    if ui.button("+").clicked() {
-                        if let Some(path) =
-                            rfd::FileDialog::new().set_file_name("hl.exe").pick_file()
-                        {
-                            if path
-                                .file_name()
-                                .is_some_and(|filename| filename == hl_exe_file_name)
-                            {
-                                current_profile.hlexe = path.display().to_string();
-                                should_save_file = true;
-                            }
-                        }
+            egui::Window::new("Half-Life is already running")
+                .collapsible(false)
+                .resizable(false)
+                .open(&mut open)
+                .show(ctx, |ui| {
+                    if self.running_games.is_empty() {
+                        ui.label("No other Half-Life running anymore");
                     }
 
-                    #[cfg(not(windows))]
-                    {
-                        use egui::include_image;
+                    let mut killed = None;
 
-                        let image = if use_windows_files {
-                            include_image!("../res/windows-brands-solid-full.svg")
-                        } else {
-                            include_image!("../res/linux-brands-solid-full.svg")
-                        };
-                        let image = egui::Image::new(image)
-                            .maintain_aspect_ratio(true)
-                            .shrink_to_fit();
-
-                        let image_button = egui::Button::image(image)
-                            .frame(false)
-                            .corner_radius(0)
-                            .small();
-
-                        if ui.add(image_button).clicked() {
-                            current_profile.use_wine = !current_profile.use_wine;
-                        }
-                    }
-                    ui.end_row();
+                    egui::Grid::new("running games").show(ui, |ui| {
+                        for game in &self.running_games {
+                            ui.label(format!("{} (pid {})", game.name, game.pid));
 
-                    ui.label("BunnymodXT");
-                    if ui
-                        .add_enabled(
-                            current_profile.enable_bxt,
-                            egui::TextEdit::singleline(&mut current_profile.bxt)
-                                .hint_text(format!("Drag-and-drop {}", bxt_file_name)),
-                        )
-                        .lost_focus()
-                    {
-                        should_save_file = true;
-                    }
+                            if ui.button("Focus").clicked()
+                                && let Err(err) = instances::focus(game.pid)
+                            {
+                                self.status.error(err.to_string());
+                            }
 
-                    if ui.button("+").clicked() {
-                        if let Some(path) = rfd::FileDialog::new()
-                            .set_file_name(bxt_file_name)
-                            .pick_file()
-                        {
-                            if path.file_name().is_some_and(|name| name == bxt_file_name) {
-                                current_profile.bxt = path.display().to_string();
-                                current_profile.enable_bxt = true;
-                                should_save_file = true;
+                            if ui.button("Kill").clicked() {
+                                match instances::kill(game.pid) {
+                                    Ok(()) => killed = Some(game.pid),
+                                    Err(err) => self.status.error(err.to_string()),
+                                }
                             }
+                            ui.end_row();
                         }
-                    }
-                    if ui
-                        .checkbox(&mut current_profile.enable_bxt, "")
-                        .on_hover_text("Toggle BunnymodXT")
-                        .changed()
-                    {
-                        should_save_file = true;
-                    }
-
-                    ui.end_row();
+                    });
 
-                    ui.label("bxt-rs");
-                    if ui
-                        .add_enabled(
-                            current_profile.enable_bxt_rs,
-                            egui::TextEdit::singleline(&mut current_profile.bxt_rs)
-                                .hint_text(format!("Drag-and-drop {}", bxt_rs_file_name)),
-                        )
-                        .lost_focus()
-                    {
-                        should_save_file = true;
+                    if let Some(pid) = killed {
+                        self.running_games.retain(|game| game.pid != pid);
                     }
 
-                    if ui.button("+").clicked() {
-                        if let Some(path) = rfd::FileDialog::new()
-                            .set_file_name(bxt_rs_file_name)
-                            .pick_file()
-                        {
-                            if path
-                                .file_name()
-                                .is_some_and(|name| name == bxt_rs_file_name)
-                            {
-                                current_profile.bxt_rs = path.display().to_string();
-                                current_profile.enable_bxt_rs = true;
-                                should_save_file = true;
-                            }
-                        }
-                    }
+                    ui.separator();
 
-                    if ui
-                        .checkbox(&mut current_profile.enable_bxt_rs, "")
-                        .on_hover_text("Toggle bxt-rs")
-                        .changed()
-                    {
-                        should_save_file = true;
-                    }
-                    ui.end_row();
+                    let launch_text = if self.running_games.is_empty() {
+                        "Launch"
+                    } else {
+                        "Launch anyway"
+                    };
 
-                    ui.label("Gamemod");
+                    let launch_button = ui.button(launch_text);
+                    #[cfg(windows)]
+                    let launch_button = launch_button.on_hover_text(
+                        "Half-Life might refuse to start while another copy is running",
+                    );
 
-                    if ui
-                        .add(
-                            egui::TextEdit::singleline(&mut current_profile.gamemod)
-                                .hint_text("valve"),
-                        )
-                        .lost_focus()
-                    {
-                        should_save_file = true;
+                    if launch_button.clicked() {
+                        launch_anyway = true;
                     }
+                });
 
-                    ui.end_row();
+            if launch_anyway && let Some(launch) = self.pending_launch.take() {
+                self.status.info(launch.status.clone());
+                self.sessions.push(launch.start(ctx));
+            } else if !open {
+                self.pending_launch = None;
+                self.status.info("Launch cancelled");
+            }
+        }
 
-                    ui.label("Extra options");
-                    if ui
-                        .add(
-                            egui::TextEdit::singleline(&mut current_profile.extras)
-                                .hint_text("More launch options"),
-                        )
-                        .lost_focus()
-                    {
-                        should_save_file = true
-                    };
+        let mut should_save_file = false;
+        let mut should_run = false;
 
-                    ui.end_row();
-                });
+        let mut configs = self.config.lock();
+        let profle_count = configs.configs.len();
 
-            ui.separator();
+        remember_window(ctx, &mut configs.window);
+        self.validator
+            .update(&configs.configs[configs.current_profile], ctx);
 
-            // drop current_profile
-            // let _ = current_profile;
-            let _ = current_profile;
+        // profiles and run button stay at the bottom so the settings above can grow
+        egui::TopBottomPanel::bottom("run panel").show(ctx, |ui| {
+            let current_profile_index = configs.current_profile;
 
             // generational
             ui.horizontal(|ui| {
-                // let current_profile = configs.current_profile;
-
                 for x in 0..profle_count {
+                    let stats = self.history.profile_stats(x + 1);
+
                     if ui
                         .selectable_label(current_profile_index == x, format!("Profile {}", x + 1))
+                        .on_hover_text(match stats.last_used {
+                            Some(last_used) => format!(
+                                "Played {}\nLast used {}",
+                                history::format_duration(stats.playtime),
+                                history::format_time(last_used)
+                            ),
+                            None => "Never launched".to_owned(),
+                        })
                         .clicked()
                     {
                         configs.current_profile = x;
@@ -306,72 +388,1073 @@ impl eframe::App for BxtLauncher {
 
             ui.separator();
 
-            let mut should_run = false;
+            let launcher = backend::launcher_for(&configs.configs[configs.current_profile]);
+
+            let errors = self
+                .validator
+                .errors()
+                .map(|problem| problem.message.as_str())
+                .collect::<Vec<_>>();
+
             ui.horizontal(|ui| {
-                if ui.button("Run").clicked() {
+                if ui
+                    .add_enabled(errors.is_empty(), egui::Button::new("Run"))
+                    .on_hover_text(format!("Starts Half-Life with {}", launcher.name()))
+                    .on_disabled_hover_text(format!("Fix these first:\n{}", errors.join("\n")))
+                    .clicked()
+                {
                     // save file first and then run
                     should_save_file = true;
                     should_run = true;
                 }
 
-                // status text
-                let mut text = self.status.as_str();
-                ui.text_edit_singleline(&mut text);
-            });
+                if ui.button("History").clicked() {
+                    self.show_history = !self.show_history;
+                }
+
+                if ui
+                    .selectable_label(self.show_log, "Log")
+                    .on_hover_text("Everything the launcher did")
+                    .clicked()
+                {
+                    self.show_log = !self.show_log;
+                }
 
-            if should_save_file {
-                if let Err(err) = configs.write_to_default() {
-                    self.status = err.to_string();
+                // latest status
+                match self.status.latest() {
+                    Some(entry) => {
+                        ui.add(
+                            egui::Label::new(
+                                egui::RichText::new(&entry.message)
+                                    .color(level_color(ui, entry.level)),
+                            )
+                            .truncate(),
+                        )
+                        .on_hover_text(entry.to_string());
+                    }
+                    None => {
+                        ui.label("Idle");
+                    }
                 }
+            });
+
+            if self.show_log {
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if ui.button("Copy").on_hover_text("Copy the log").clicked() {
+                        ui.ctx().copy_text(self.status.to_text());
+                    }
+
+                    if ui.button("Clear").clicked() {
+                        self.status.clear();
+                    }
+                });
+
+                egui::ScrollArea::vertical()
+                    .max_height(120.)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for entry in self.status.entries() {
+                            ui.horizontal(|ui| {
+                                ui.label(egui::RichText::new(entry.short_time()).weak());
+                                ui.label(
+                                    egui::RichText::new(entry.level.label())
+                                        .color(level_color(ui, entry.level)),
+                                );
+                                ui.label(&entry.message);
+                            });
+                        }
+                    });
             }
 
-            if should_run {
-                // pick config up again
-                let current_profile_index = configs.current_profile;
-                let current_profile = &mut configs.configs[current_profile_index];
+            if let Some(hint) = &self.hint {
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new(&hint.text).weak());
 
-                match run_bxt(&current_profile) {
-                    Ok(_) => self.status = "OK".into(),
-                    Err(err) => self.status = err.to_string(),
-                };
+                    if let Some(field) = hint.field
+                        && ui
+                            .button("Show")
+                            .on_hover_text("Go to the setting")
+                            .clicked()
+                    {
+                        self.jump_to = Some(field);
+                    }
+                });
             }
+        });
+
+        let current_profile_index = configs.current_profile;
+
+        let current_profile = &mut configs.configs[current_profile_index];
+
+        let use_windows_files = backend::launcher_for(current_profile).uses_windows_files();
+
+        let hl_exe_file_name = if cfg!(windows) || use_windows_files {
+            HL_EXE_FILE_NAME_WINDOWS
+        } else {
+            HL_EXE_FILE_NAME_LINUX
+        };
+
+        let bxt_file_name = if cfg!(windows) || use_windows_files {
+            BXT_FILE_NAME_WINDOWS
+        } else {
+            BXT_FILE_NAME_LINUX
+        };
+
+        let bxt_rs_file_name = if cfg!(windows) || use_windows_files {
+            BXT_RS_FILE_NAME_WINDOWS
+        } else {
+            BXT_RS_FILE_NAME_LINUX
+        };
 
-            preview_file_being_dropped(ctx);
+        let mut marks = FieldMarks {
+            jump_to: &mut self.jump_to,
+            problems: self.validator.problems(),
+        };
 
-            // Collect dropped files:
-            ctx.input(|i| {
-                if i.raw.dropped_files.len() == 1 {
-                    let item = i.raw.dropped_files[0].clone();
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                // text fields grow with the window, labels and buttons keep their size
+                let field_width = (ui.available_width() - 200.).max(160.);
+                ui.spacing_mut().text_edit_width = field_width;
+
+                egui::Grid::new("ui grid")
+                    .num_columns(4)
+                    .max_col_width(field_width)
+                    .min_col_width(8.)
+                    .show(ui, |ui| {
+                        let label = ui.label(hl_exe_file_name);
+                        if use_windows_files && !current_profile.hlexe.as_os_str().is_empty() {
+                            label.on_hover_ui(|ui| {
+                                ui.label(pe_summary(&mut self.pe_info, &current_profile.hlexe));
+                            });
+                        }
+                        let response = path_edit(
+                            ui,
+                            true,
+                            &mut current_profile.hlexe,
+                            format!("Drag-and-drop {}", hl_exe_file_name),
+                        );
+                        marks.apply(ui, &response, Field::HlExe);
+                        if response.lost_focus() {
+                            should_save_file = true;
+                        }
+
+                        ui.horizontal(|ui| {
+                            if ui.button("+").clicked()
+                                && let Some(path) =
+                                    rfd::FileDialog::new().set_file_name("hl.exe").pick_file()
+                                && path
+                                    .file_name()
+                                    .is_some_and(|filename| filename == hl_exe_file_name)
+                            {
+                                current_profile.hlexe = path;
+                                should_save_file = true;
+                            }
+
+                            let picker = ui.menu_button("▼", |ui| {
+                                if self.hl_installs.is_empty() {
+                                    ui.label("No Half-Life found in Steam libraries");
+                                }
+
+                                for install in &self.hl_installs {
+                                    if ui.button(install.display().to_string()).clicked() {
+                                        current_profile.hlexe = install.clone();
+                                        should_save_file = true;
+                                        ui.close();
+                                    }
+                                }
+                            });
+
+                            if picker.response.clicked() {
+                                self.hl_installs = steam::find_half_life_installs(hl_exe_file_name);
+                            }
 
-                    // borrow again
-                    let current_profile = &mut configs.configs[current_profile_index];
+                            picker
+                                .response
+                                .on_hover_text("Find Half-Life in Steam libraries");
+                        });
 
-                    if let Some(item) = item.path {
-                        if item
-                            .file_name()
-                            .is_some_and(|filename| filename == hl_exe_file_name)
+                        #[cfg(not(windows))]
                         {
-                            current_profile.hlexe = item.to_str().unwrap().to_string();
+                            use egui::include_image;
+
+                            let image = if use_windows_files {
+                                include_image!("../res/windows-brands-solid-full.svg")
+                            } else {
+                                include_image!("../res/linux-brands-solid-full.svg")
+                            };
+                            let image = egui::Image::new(image)
+                                .maintain_aspect_ratio(true)
+                                .shrink_to_fit();
+
+                            let image_button = egui::Button::image(image)
+                                .frame(false)
+                                .corner_radius(0)
+                                .small();
+
+                            if ui.add(image_button).clicked() {
+                                current_profile.use_wine = !current_profile.use_wine;
+                            }
+                        }
+                        ui.end_row();
+
+                        let label = ui.label("BunnymodXT");
+                        if use_windows_files && !current_profile.bxt.as_os_str().is_empty() {
+                            label.on_hover_ui(|ui| {
+                                ui.label(pe_summary(&mut self.pe_info, &current_profile.bxt));
+                            });
+                        }
+                        let response = path_edit(
+                            ui,
+                            current_profile.enable_bxt,
+                            &mut current_profile.bxt,
+                            format!("Drag-and-drop {}", bxt_file_name),
+                        );
+                        marks.apply(ui, &response, Field::Bxt);
+                        if response.lost_focus() {
+                            should_save_file = true;
                         }
 
-                        if item
-                            .file_name()
-                            .is_some_and(|filename| filename == bxt_file_name)
+                        if ui.button("+").clicked()
+                            && let Some(path) = rfd::FileDialog::new()
+                                .set_file_name(bxt_file_name)
+                                .pick_file()
+                            && path.file_name().is_some_and(|name| name == bxt_file_name)
                         {
-                            current_profile.bxt = item.to_str().unwrap().to_string();
+                            current_profile.bxt = path;
                             current_profile.enable_bxt = true;
+                            should_save_file = true;
+                        }
+                        if ui
+                            .checkbox(&mut current_profile.enable_bxt, "")
+                            .on_hover_text("Toggle BunnymodXT")
+                            .changed()
+                        {
+                            should_save_file = true;
                         }
 
-                        if item
-                            .file_name()
-                            .is_some_and(|filename| filename == bxt_rs_file_name)
+                        ui.end_row();
+
+                        let label = ui.label("bxt-rs");
+                        if use_windows_files && !current_profile.bxt_rs.as_os_str().is_empty() {
+                            label.on_hover_ui(|ui| {
+                                ui.label(pe_summary(&mut self.pe_info, &current_profile.bxt_rs));
+                            });
+                        }
+                        let response = path_edit(
+                            ui,
+                            current_profile.enable_bxt_rs,
+                            &mut current_profile.bxt_rs,
+                            format!("Drag-and-drop {}", bxt_rs_file_name),
+                        );
+                        marks.apply(ui, &response, Field::BxtRs);
+                        if response.lost_focus() {
+                            should_save_file = true;
+                        }
+
+                        if ui.button("+").clicked()
+                            && let Some(path) = rfd::FileDialog::new()
+                                .set_file_name(bxt_rs_file_name)
+                                .pick_file()
+                            && path
+                                .file_name()
+                                .is_some_and(|name| name == bxt_rs_file_name)
                         {
-                            current_profile.bxt_rs = item.to_str().unwrap().to_string();
+                            current_profile.bxt_rs = path;
                             current_profile.enable_bxt_rs = true;
+                            should_save_file = true;
+                        }
+
+                        if ui
+                            .checkbox(&mut current_profile.enable_bxt_rs, "")
+                            .on_hover_text("Toggle bxt-rs")
+                            .changed()
+                        {
+                            should_save_file = true;
+                        }
+                        ui.end_row();
+
+                        should_save_file |=
+                            plugin_rows(ui, &mut current_profile.plugins, &mut marks);
+
+                        should_save_file |= text_row(
+                            ui,
+                            "Gamemod",
+                            &mut current_profile.gamemod,
+                            "valve",
+                            Field::Gamemod,
+                            &mut marks,
+                        );
+                        should_save_file |= text_row(
+                            ui,
+                            "Extra options",
+                            &mut current_profile.extras,
+                            "More launch options",
+                            Field::Extras,
+                            &mut marks,
+                        );
+
+                        ui.label("Pre-launch");
+                        let response = ui.add(
+                            egui::TextEdit::multiline(&mut current_profile.pre_launch_hooks)
+                                .desired_rows(1)
+                                .hint_text("Commands run before launch, one per line"),
+                        );
+                        marks.apply(ui, &response, Field::PreLaunchHooks);
+                        if response.lost_focus() {
+                            should_save_file = true;
+                        }
+                        ui.end_row();
+
+                        ui.label("Post-exit");
+                        let response = ui.add(
+                            egui::TextEdit::multiline(&mut current_profile.post_exit_hooks)
+                                .desired_rows(1)
+                                .hint_text("Commands run after the game exits, one per line"),
+                        );
+                        marks.apply(ui, &response, Field::PostExitHooks);
+                        if response.lost_focus() {
+                            should_save_file = true;
+                        }
+                        ui.end_row();
+
+                        ui.label("Priority");
+                        let response = ui
+                            .add(egui::DragValue::new(&mut current_profile.nice).range(-20..=19))
+                            .on_hover_text(PRIORITY_HINT);
+                        marks.apply(ui, &response, Field::Priority);
+                        if response.changed() {
+                            should_save_file = true;
+                        }
+                        ui.end_row();
+
+                        ui.label("Startup timeout");
+                        let response = ui
+                            .add(
+                                egui::DragValue::new(&mut current_profile.startup_timeout)
+                                    .range(0..=600)
+                                    .suffix(" s"),
+                            )
+                            .on_hover_text("How long BXT gets to load, 0 waits forever");
+                        marks.apply(ui, &response, Field::StartupTimeout);
+                        if response.changed() {
+                            should_save_file = true;
+                        }
+
+                        if ui
+                            .checkbox(&mut current_profile.kill_on_timeout, "")
+                            .on_hover_text("Kill the game if BXT has not loaded by then")
+                            .changed()
+                        {
+                            should_save_file = true;
+                        }
+                        ui.end_row();
+
+                        should_save_file |= text_row(
+                            ui,
+                            "CPU affinity",
+                            &mut current_profile.cpu_affinity,
+                            "All CPUs, or a list like 0-3,6",
+                            Field::CpuAffinity,
+                            &mut marks,
+                        );
+
+                        #[cfg(not(windows))]
+                        {
+                            use crate::scheduling::IoClass;
+
+                            ui.label("I/O priority");
+                            ui.horizontal(|ui| {
+                                egui::ComboBox::from_id_salt("io class")
+                                    .selected_text(current_profile.io_class.label())
+                                    .show_ui(ui, |ui| {
+                                        for class in IoClass::ALL {
+                                            if ui
+                                                .selectable_value(
+                                                    &mut current_profile.io_class,
+                                                    class,
+                                                    class.label(),
+                                                )
+                                                .changed()
+                                            {
+                                                should_save_file = true;
+                                            }
+                                        }
+                                    });
+
+                                if current_profile.io_class.has_level()
+                                    && ui
+                                        .add(
+                                            egui::DragValue::new(&mut current_profile.io_level)
+                                                .range(0..=7),
+                                        )
+                                        .on_hover_text("0 is the highest, 7 the lowest")
+                                        .changed()
+                                {
+                                    should_save_file = true;
+                                }
+                            });
+                            ui.end_row();
+                        }
+
+                        #[cfg(not(windows))]
+                        if current_profile.use_wine {
+                            use crate::config::WineRunner;
+
+                            ui.label("Runner");
+                            egui::ComboBox::from_id_salt("wine runner")
+                                .selected_text(current_profile.wine_runner.label())
+                                .show_ui(ui, |ui| {
+                                    for runner in WineRunner::ALL {
+                                        if ui
+                                            .selectable_value(
+                                                &mut current_profile.wine_runner,
+                                                runner,
+                                                runner.label(),
+                                            )
+                                            .changed()
+                                        {
+                                            should_save_file = true;
+                                        }
+                                    }
+                                });
+                            ui.end_row();
+
+                            match current_profile.wine_runner {
+                                WineRunner::Wine => {
+                                    should_save_file |= path_row(
+                                        ui,
+                                        "Wine",
+                                        &mut current_profile.wine_binary,
+                                        "wine",
+                                        || rfd::FileDialog::new().pick_file(),
+                                        Field::WineBinary,
+                                        &mut marks,
+                                    );
+                                    should_save_file |= path_row(
+                                        ui,
+                                        "WINEPREFIX",
+                                        &mut current_profile.wine_prefix,
+                                        "~/.wine",
+                                        || rfd::FileDialog::new().pick_folder(),
+                                        Field::WinePrefix,
+                                        &mut marks,
+                                    );
+                                }
+                                WineRunner::Proton => {
+                                    ui.label("Proton");
+                                    let response = path_edit(
+                                        ui,
+                                        true,
+                                        &mut current_profile.proton,
+                                        "Proton directory",
+                                    );
+                                    marks.apply(ui, &response, Field::Proton);
+                                    if response.lost_focus() {
+                                        should_save_file = true;
+                                    }
+
+                                    ui.horizontal(|ui| {
+                                        if ui.button("+").clicked()
+                                            && let Some(path) = rfd::FileDialog::new().pick_folder()
+                                        {
+                                            current_profile.proton = path;
+                                            should_save_file = true;
+                                        }
+
+                                        let picker = ui.menu_button("▼", |ui| {
+                                            if self.proton_installs.is_empty() {
+                                                ui.label("No Proton found");
+                                            }
+
+                                            for install in &self.proton_installs {
+                                                if ui
+                                                    .button(&install.name)
+                                                    .on_hover_text(
+                                                        install.path.display().to_string(),
+                                                    )
+                                                    .clicked()
+                                                {
+                                                    current_profile.proton = install.path.clone();
+                                                    should_save_file = true;
+                                                    ui.close();
+                                                }
+                                            }
+                                        });
+
+                                        if picker.response.clicked() {
+                                            self.proton_installs = steam::find_proton_installs();
+                                        }
+
+                                        picker.response.on_hover_text("Find installed Proton");
+                                    });
+                                    ui.end_row();
+
+                                    should_save_file |= path_row(
+                                        ui,
+                                        "Compat data",
+                                        &mut current_profile.proton_compat_data,
+                                        "steamapps/compatdata/70 next to Half-Life",
+                                        || rfd::FileDialog::new().pick_folder(),
+                                        Field::ProtonCompatData,
+                                        &mut marks,
+                                    );
+                                }
+                            }
+
+                            should_save_file |= text_row(
+                                ui,
+                                "DLL overrides",
+                                &mut current_profile.wine_dll_overrides,
+                                "WINEDLLOVERRIDES",
+                                Field::WineDllOverrides,
+                                &mut marks,
+                            );
+                            should_save_file |= text_row(
+                                ui,
+                                "WINEDEBUG",
+                                &mut current_profile.wine_debug,
+                                "-all",
+                                Field::WineDebug,
+                                &mut marks,
+                            );
+                            should_save_file |= path_row(
+                                ui,
+                                "Injector",
+                                &mut current_profile.wine_injector,
+                                "bxt-launcher.exe, loads BXT inside the prefix",
+                                || rfd::FileDialog::new().pick_file(),
+                                Field::WineInjector,
+                                &mut marks,
+                            );
+                        } else {
+                            use crate::config::SteamRuntime;
+
+                            ui.label("Runtime");
+                            let combo = egui::ComboBox::from_id_salt("steam runtime")
+                                .selected_text(current_profile.steam_runtime.label())
+                                .show_ui(ui, |ui| {
+                                    for runtime in SteamRuntime::ALL {
+                                        if ui
+                                            .selectable_value(
+                                                &mut current_profile.steam_runtime,
+                                                runtime,
+                                                runtime.label(),
+                                            )
+                                            .changed()
+                                        {
+                                            should_save_file = true;
+                                        }
+                                    }
+                                });
+                            marks.apply(ui, &combo.response, Field::Runtime);
+                            ui.end_row();
+
+                            if current_profile.steam_runtime == SteamRuntime::Custom {
+                                should_save_file |= path_row(
+                                    ui,
+                                    "Runtime script",
+                                    &mut current_profile.custom_runtime,
+                                    "Script that runs the game",
+                                    || rfd::FileDialog::new().pick_file(),
+                                    Field::CustomRuntime,
+                                    &mut marks,
+                                );
+                            }
+
+                            ui.label("");
+                            if ui
+                                .checkbox(
+                                    &mut current_profile.strip_inherited_paths,
+                                    "Ignore inherited LD_PRELOAD and LD_LIBRARY_PATH",
+                                )
+                                .changed()
+                            {
+                                should_save_file = true;
+                            }
+                            ui.end_row();
                         }
+
+                        #[cfg(not(windows))]
+                        {
+                            ui.label("");
+                            if ui
+                                .checkbox(
+                                    &mut current_profile.clean_environment,
+                                    "Start from a clean environment",
+                                )
+                                .changed()
+                            {
+                                should_save_file = true;
+                            }
+
+                            if ui
+                                .button("View")
+                                .on_hover_text("Show the environment the game would get")
+                                .clicked()
+                            {
+                                self.environment_view = Some(
+                                    backend::launch_plan(current_profile)
+                                        .map(|plan| plan.environment())
+                                        .map_err(|err| err.to_string()),
+                                );
+                            }
+                            ui.end_row();
+
+                            if current_profile.clean_environment {
+                                should_save_file |= text_row(
+                                    ui,
+                                    "Keep variables",
+                                    &mut current_profile.environment_allowlist,
+                                    "More variables to keep, like SDL_* or MANGOHUD",
+                                    Field::EnvironmentAllowlist,
+                                    &mut marks,
+                                );
+                            }
+                        }
+                    });
+            });
+        });
+
+        // the setting is not shown for this profile, nowhere to go
+        self.jump_to = None;
+
+        if should_save_file && let Err(err) = configs.write_to_default() {
+            self.status.error(err.to_string());
+        }
+
+        let mut launch = None;
+
+        if should_run {
+            // pick config up again
+            let current_profile_index = configs.current_profile;
+            let current_profile = &configs.configs[current_profile_index];
+
+            launch = Some(PendingLaunch {
+                config: current_profile.clone(),
+                plan: None,
+                profile_index: current_profile_index,
+                status: "Launching".into(),
+            });
+        }
+
+        if let Some(record) = relaunch {
+            let changed = record.changed_libraries();
+
+            let status = if changed.is_empty() {
+                "Relaunching".into()
+            } else {
+                let changed = changed
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>();
+
+                format!("Relaunching, changed since then: {}", changed.join(", "))
+            };
+
+            launch = Some(PendingLaunch {
+                config: record.config.clone(),
+                plan: Some(record.plan.clone()),
+                profile_index: record.profile.saturating_sub(1),
+                status,
+            });
+        }
+
+        if let Some(launch) = launch {
+            self.running_games = instances::find_running_games();
+
+            self.hint = None;
+
+            if self.running_games.is_empty() {
+                self.status.info(launch.status.clone());
+                self.sessions.push(launch.start(ctx));
+            } else {
+                self.status.warn("Half-Life is already running");
+                self.pending_launch = Some(launch);
+            }
+        }
+
+        preview_file_being_dropped(ctx);
+
+        // Collect dropped files:
+        ctx.input(|i| {
+            if i.raw.dropped_files.len() == 1 {
+                let item = i.raw.dropped_files[0].clone();
+
+                // borrow again
+                let current_profile = &mut configs.configs[current_profile_index];
+
+                if let Some(item) = item.path {
+                    if item
+                        .file_name()
+                        .is_some_and(|filename| filename == hl_exe_file_name)
+                    {
+                        current_profile.hlexe = item.clone();
+                    }
+
+                    if item
+                        .file_name()
+                        .is_some_and(|filename| filename == bxt_file_name)
+                    {
+                        current_profile.bxt = item.clone();
+                        current_profile.enable_bxt = true;
+                    }
+
+                    if item
+                        .file_name()
+                        .is_some_and(|filename| filename == bxt_rs_file_name)
+                    {
+                        current_profile.bxt_rs = item;
+                        current_profile.enable_bxt_rs = true;
                     }
                 }
-            });
+            }
         });
     }
 }
+
+/// One grid row per plugin with buttons to reorder and remove it, then a row to add one.
+///
+/// Returns whether the list should be saved.
+fn plugin_rows(ui: &mut egui::Ui, plugins: &mut Vec<Plugin>, marks: &mut FieldMarks) -> bool {
+    enum Action {
+        Up(usize),
+        Down(usize),
+        Remove(usize),
+    }
+
+    let mut should_save_file = false;
+    let mut action = None;
+    let count = plugins.len();
+
+    for (index, plugin) in plugins.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.label(format!("Plugin {}", index + 1));
+
+            if ui
+                .add_enabled(index > 0, egui::Button::new("↑").small())
+                .clicked()
+            {
+                action = Some(Action::Up(index));
+            }
+
+            if ui
+                .add_enabled(index + 1 < count, egui::Button::new("↓").small())
+                .clicked()
+            {
+                action = Some(Action::Down(index));
+            }
+
+            if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                action = Some(Action::Remove(index));
+            }
+        });
+
+        let response = path_edit(
+            ui,
+            plugin.enabled,
+            &mut plugin.path,
+            "Library loaded after BXT",
+        );
+        marks.apply(ui, &response, Field::Plugin(index));
+        if response.lost_focus() {
+            should_save_file = true;
+        }
+
+        if ui.button("+").clicked()
+            && let Some(path) = rfd::FileDialog::new().pick_file()
+        {
+            plugin.path = path;
+            plugin.enabled = true;
+            should_save_file = true;
+        }
+
+        if ui
+            .checkbox(&mut plugin.enabled, "")
+            .on_hover_text("Toggle plugin")
+            .changed()
+        {
+            should_save_file = true;
+        }
+        ui.end_row();
+    }
+
+    match action {
+        Some(Action::Up(index)) => plugins.swap(index, index - 1),
+        Some(Action::Down(index)) => plugins.swap(index, index + 1),
+        Some(Action::Remove(index)) => {
+            plugins.remove(index);
+        }
+        None => (),
+    }
+
+    ui.label("");
+    if ui.button("Add plugin").clicked() {
+        plugins.push(Plugin {
+            path: PathBuf::new(),
+            enabled: true,
+        });
+    }
+    ui.end_row();
+
+    should_save_file | action.is_some()
+}
+
+/// Label and text field taking up a whole grid row.
+///
+/// Returns whether the value should be saved.
+fn text_row(
+    ui: &mut egui::Ui,
+    label: &str,
+    value: &mut String,
+    hint: &str,
+    field: Field,
+    marks: &mut FieldMarks,
+) -> bool {
+    ui.label(label);
+    let response = ui.add(egui::TextEdit::singleline(value).hint_text(hint));
+    marks.apply(ui, &response, field);
+    ui.end_row();
+
+    response.lost_focus()
+}
+
+/// Label, path field and file picker button taking up a whole grid row.
+///
+/// Returns whether the path should be saved.
+fn path_row(
+    ui: &mut egui::Ui,
+    label: &str,
+    path: &mut PathBuf,
+    hint: &str,
+    picker: fn() -> Option<PathBuf>,
+    field: Field,
+    marks: &mut FieldMarks,
+) -> bool {
+    let mut should_save_file = false;
+
+    ui.label(label);
+    let response = path_edit(ui, true, path, hint);
+    marks.apply(ui, &response, field);
+    if response.lost_focus() {
+        should_save_file = true;
+    }
+
+    if ui.button("+").clicked()
+        && let Some(picked) = picker()
+    {
+        *path = picked;
+        should_save_file = true;
+    }
+
+    ui.end_row();
+
+    should_save_file
+}
+
+/// Text field for a path. The path is only replaced once the text is edited, so one that is not
+/// valid UTF-8 stays as it is while it is only shown.
+fn path_edit(
+    ui: &mut egui::Ui,
+    enabled: bool,
+    path: &mut PathBuf,
+    hint: impl Into<egui::WidgetText>,
+) -> egui::Response {
+    let mut text = path.to_string_lossy().into_owned();
+    let response = ui.add_enabled(
+        enabled,
+        egui::TextEdit::singleline(&mut text).hint_text(hint),
+    );
+
+    if response.changed() {
+        *path = PathBuf::from(text);
+    }
+
+    response
+}
+
+/// Keeps the window geometry and zoom in the config so they are saved with it.
+fn remember_window(ctx: &egui::Context, window: &mut WindowState) {
+    let zoom = ctx.zoom_factor();
+
+    ctx.input(|i| {
+        let viewport = i.viewport();
+
+        // a minimized window has no useful geometry
+        if viewport.minimized == Some(true) {
+            return;
+        }
+
+        // points are zoomed, the viewport builder takes logical pixels
+        if let Some(rect) = viewport.inner_rect {
+            window.size = Some([rect.width() * zoom, rect.height() * zoom]);
+        }
+
+        if let Some(rect) = viewport.outer_rect {
+            window.position = Some([rect.min.x * zoom, rect.min.y * zoom]);
+        }
+    });
+
+    window.zoom = Some(zoom);
+}
+
+fn level_color(ui: &egui::Ui, level: Level) -> egui::Color32 {
+    match level {
+        Level::Info => ui.visuals().text_color(),
+        Level::Warn => ui.visuals().warn_fg_color,
+        Level::Error => ui.visuals().error_fg_color,
+    }
+}
+
+/// What the settings grid shows on top of each field: where the user asked to be taken, and what
+/// is wrong with it.
+struct FieldMarks<'a> {
+    jump_to: &'a mut Option<Field>,
+    problems: &'a Problems,
+}
+
+impl FieldMarks<'_> {
+    /// Scrolls to and focuses the widget if the user asked for `field`, and outlines it with the
+    /// problem on hover if there is one.
+    fn apply(&mut self, ui: &egui::Ui, response: &egui::Response, field: Field) {
+        if *self.jump_to == Some(field) {
+            response.request_focus();
+            response.scroll_to_me(Some(egui::Align::Center));
+            *self.jump_to = None;
+        }
+
+        let Some(problem) = self.problems.get(&Some(field)) else {
+            return;
+        };
+
+        let color = match problem.severity {
+            Severity::Warning => ui.visuals().warn_fg_color,
+            Severity::Error => ui.visuals().error_fg_color,
+        };
+
+        ui.painter().rect_stroke(
+            response.rect.expand(1.),
+            2.,
+            egui::Stroke::new(1.5, color),
+            egui::StrokeKind::Outside,
+        );
+        response.clone().on_hover_text(&problem.message);
+    }
+}
+
+/// Past launches, newest first, each with its launch plan on hover and a button to run it again.
+fn history_list(ui: &mut egui::Ui, history: &History, relaunch: &mut Option<LaunchRecord>) {
+    if history.launches.is_empty() {
+        ui.label("Nothing launched yet");
+        return;
+    }
+
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        egui::Grid::new("history grid")
+            .num_columns(4)
+            .striped(true)
+            .show(ui, |ui| {
+                for record in history.launches.iter().rev() {
+                    ui.label(history::format_time(record.started))
+                        .on_hover_text(record.plan.to_string());
+                    ui.label(format!("Profile {}", record.profile));
+
+                    let outcome = match (record.duration(), record.exit_status()) {
+                        (Some(duration), Some(status)) => format!(
+                            "{}, exited with {status}",
+                            history::format_duration(duration)
+                        ),
+                        _ => "Unknown".to_owned(),
+                    };
+                    ui.label(outcome);
+
+                    if ui
+                        .button("Relaunch")
+                        .on_hover_text("Run again with the settings it had back then")
+                        .clicked()
+                    {
+                        *relaunch = Some(record.clone());
+                    }
+                    ui.end_row();
+                }
+            });
+    });
+}
+
+/// Variables the game would get and where each one comes from, removed ones struck through.
+#[cfg(not(windows))]
+fn environment_list(ui: &mut egui::Ui, view: &Result<Vec<launch::EnvVar>, String>) {
+    use crate::launch::EnvSource;
+
+    let vars = match view {
+        Ok(vars) => vars,
+        Err(err) => {
+            ui.label(err);
+            return;
+        }
+    };
+
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        egui::Grid::new("environment grid")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                for var in vars {
+                    let (source, name) = match var.source {
+                        EnvSource::Inherited => ("inherited", egui::RichText::new(&var.name)),
+                        EnvSource::Overridden => {
+                            ("overridden", egui::RichText::new(&var.name).strong())
+                        }
+                        EnvSource::Removed => (
+                            "removed",
+                            egui::RichText::new(&var.name).strikethrough().weak(),
+                        ),
+                    };
+
+                    ui.label(name);
+                    ui.label(source);
+                    ui.label(&var.value);
+                    ui.end_row();
+                }
+            });
+    });
+}
+
+/// Parsed Windows files by path, along with their modification time to know when to parse again.
+type PeInfoCache = HashMap<PathBuf, (Option<SystemTime>, Result<PeInfo, String>)>;
+
+/// Architecture, version and link time of a Windows file, for hover text.
+fn pe_summary(cache: &mut PeInfoCache, path: &Path) -> String {
+    let modified = std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok();
+
+    let info = match cache.get(path) {
+        Some((cached_modified, info)) if *cached_modified == modified => info,
+        _ => {
+            let info = PeInfo::read(path).map_err(|err| err.to_string());
+
+            &cache
+                .entry(path.into())
+                .insert_entry((modified, info))
+                .into_mut()
+                .1
+        }
+    };
+
+    match info {
+        Ok(info) => {
+            let version = info.version_string().unwrap_or("unknown".to_owned());
+
+            format!(
+                "{}\nVersion: {}\nBuilt: {}",
+                info.describe(),
+                version,
+                info.timestamp_string()
+            )
+        }
+        Err(err) => err.clone(),
+    }
+}
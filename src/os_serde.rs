@@ -0,0 +1,160 @@
+//! Lossless TOML for paths and other OS strings, which do not have to be UTF-8.
+//!
+//! Valid UTF-8 is written as a plain string, so files stay readable and older ones keep loading.
+//! Anything else is written as the raw units of the platform, `{ unix = [...] }` bytes or
+//! `{ windows = [...] }` UTF-16.
+//!
+//! Use with `#[serde(with = "crate::os_serde")]` on a `PathBuf` or `OsString`, or one of the
+//! submodules for containers of them.
+
+use std::ffi::{OsStr, OsString};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Repr {
+    Text(String),
+    Raw(Raw),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Raw {
+    Unix(Vec<u8>),
+    Windows(Vec<u16>),
+}
+
+impl From<&OsStr> for Repr {
+    fn from(value: &OsStr) -> Self {
+        if let Some(text) = value.to_str() {
+            return Repr::Text(text.to_owned());
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+
+            Repr::Raw(Raw::Unix(value.as_bytes().to_vec()))
+        }
+
+        #[cfg(windows)]
+        {
+            use std::os::windows::ffi::OsStrExt;
+
+            Repr::Raw(Raw::Windows(value.encode_wide().collect()))
+        }
+    }
+}
+
+impl From<Repr> for OsString {
+    fn from(value: Repr) -> Self {
+        match value {
+            Repr::Text(text) => text.into(),
+            #[cfg(unix)]
+            Repr::Raw(Raw::Unix(bytes)) => {
+                use std::os::unix::ffi::OsStringExt;
+
+                OsString::from_vec(bytes)
+            }
+            #[cfg(windows)]
+            Repr::Raw(Raw::Windows(units)) => {
+                use std::os::windows::ffi::OsStringExt;
+
+                OsString::from_wide(&units)
+            }
+            // written on the other platform, the best that can be done is a lossy copy
+            #[cfg(not(unix))]
+            Repr::Raw(Raw::Unix(bytes)) => String::from_utf8_lossy(&bytes).into_owned().into(),
+            #[cfg(not(windows))]
+            Repr::Raw(Raw::Windows(units)) => String::from_utf16_lossy(&units).into(),
+        }
+    }
+}
+
+pub fn serialize<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: AsRef<OsStr>,
+{
+    Repr::from(value.as_ref()).serialize(serializer)
+}
+
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: From<OsString>,
+{
+    Ok(OsString::from(Repr::deserialize(deserializer)?).into())
+}
+
+pub mod option {
+    use super::*;
+
+    pub fn serialize<S, T>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: AsRef<OsStr>,
+    {
+        value
+            .as_ref()
+            .map(|value| Repr::from(value.as_ref()))
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: From<OsString>,
+    {
+        Ok(Option::<Repr>::deserialize(deserializer)?.map(|repr| OsString::from(repr).into()))
+    }
+}
+
+pub mod vec {
+    use super::*;
+
+    pub fn serialize<S, T>(values: &[T], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: AsRef<OsStr>,
+    {
+        serializer.collect_seq(values.iter().map(|value| Repr::from(value.as_ref())))
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: From<OsString>,
+    {
+        Ok(Vec::<Repr>::deserialize(deserializer)?
+            .into_iter()
+            .map(|repr| OsString::from(repr).into())
+            .collect())
+    }
+}
+
+/// For `(name, value)` lists of variables, where only the value can be anything.
+pub mod env {
+    use super::*;
+
+    pub fn serialize<S>(vars: &[(String, OsString)], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(
+            vars.iter()
+                .map(|(name, value)| (name, Repr::from(value.as_os_str()))),
+        )
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<(String, OsString)>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Vec::<(String, Repr)>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(name, repr)| (name, repr.into()))
+            .collect())
+    }
+}
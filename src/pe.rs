@@ -0,0 +1,230 @@
+use std::path::Path;
+
+use crate::error::LauncherError;
+
+const IMAGE_FILE_MACHINE_I386: u16 = 0x14c;
+const IMAGE_FILE_MACHINE_AMD64: u16 = 0x8664;
+const IMAGE_FILE_MACHINE_ARM64: u16 = 0xaa64;
+
+const PE32_MAGIC: u16 = 0x10b;
+const PE32_PLUS_MAGIC: u16 = 0x20b;
+
+const RESOURCE_DIRECTORY_INDEX: usize = 2;
+const RT_VERSION: u32 = 16;
+const VS_FIXEDFILEINFO_SIGNATURE: u32 = 0xfeef04bd;
+
+/// What is read from the headers of a Windows executable or DLL.
+///
+/// Only parses bytes, so Windows files can be checked on Linux as well.
+#[derive(Debug, Clone)]
+pub struct PeInfo {
+    pub machine: u16,
+    pub is_pe32_plus: bool,
+    /// Link time as seconds since the Unix epoch, though some toolchains write a hash here
+    pub timestamp: u32,
+    /// File version from the version resource, if there is one
+    pub file_version: Option<[u16; 4]>,
+}
+
+impl PeInfo {
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        if !bytes.starts_with(b"MZ") {
+            return None;
+        }
+
+        let pe_offset = read_u32(bytes, 0x3c)? as usize;
+
+        if bytes.get(pe_offset..pe_offset + 4)? != b"PE\0\0" {
+            return None;
+        }
+
+        // COFF file header
+        let coff = pe_offset + 4;
+        let machine = read_u16(bytes, coff)?;
+        let section_count = read_u16(bytes, coff + 2)? as usize;
+        let timestamp = read_u32(bytes, coff + 4)?;
+        let optional_header_size = read_u16(bytes, coff + 16)? as usize;
+
+        let optional = coff + 20;
+        let is_pe32_plus = match read_u16(bytes, optional)? {
+            PE32_MAGIC => false,
+            PE32_PLUS_MAGIC => true,
+            _ => return None,
+        };
+
+        let sections = (0..section_count)
+            .filter_map(|index| Section::parse(bytes, optional + optional_header_size + index * 40))
+            .collect::<Vec<_>>();
+
+        // data directories come right after the fields that differ in size between the two
+        let data_directories = optional + if is_pe32_plus { 112 } else { 96 };
+        let directory_count = read_u32(bytes, data_directories - 4)? as usize;
+
+        let file_version = if RESOURCE_DIRECTORY_INDEX < directory_count {
+            let rva = read_u32(bytes, data_directories + RESOURCE_DIRECTORY_INDEX * 8)?;
+
+            read_file_version(bytes, &sections, rva)
+        } else {
+            None
+        };
+
+        Some(Self {
+            machine,
+            is_pe32_plus,
+            timestamp,
+            file_version,
+        })
+    }
+
+    pub fn read(path: &Path) -> Result<Self, LauncherError> {
+        let bytes = std::fs::read(path).map_err(|source| {
+            if source.kind() == std::io::ErrorKind::NotFound {
+                LauncherError::FileDoesNotExist { path: path.into() }
+            } else {
+                LauncherError::IOContext {
+                    operation: "read",
+                    path: path.into(),
+                    source,
+                }
+            }
+        })?;
+
+        Self::parse(&bytes).ok_or_else(|| LauncherError::NotPe { path: path.into() })
+    }
+
+    pub fn is_x86(&self) -> bool {
+        self.machine == IMAGE_FILE_MACHINE_I386 && !self.is_pe32_plus
+    }
+
+    pub fn describe(&self) -> String {
+        let bits = if self.is_pe32_plus {
+            "64-bit"
+        } else {
+            "32-bit"
+        };
+
+        let machine = match self.machine {
+            IMAGE_FILE_MACHINE_I386 => "x86".to_owned(),
+            IMAGE_FILE_MACHINE_AMD64 => "x86-64".to_owned(),
+            IMAGE_FILE_MACHINE_ARM64 => "ARM64".to_owned(),
+            machine => format!("machine {machine:#x}"),
+        };
+
+        format!("{bits} {machine}")
+    }
+
+    pub fn version_string(&self) -> Option<String> {
+        self.file_version
+            .map(|[major, minor, patch, build]| format!("{major}.{minor}.{patch}.{build}"))
+    }
+
+    pub fn timestamp_string(&self) -> String {
+        match jiff::Timestamp::from_second(self.timestamp.into()) {
+            Ok(timestamp) => timestamp.to_string(),
+            Err(_) => format!("{:#x}", self.timestamp),
+        }
+    }
+}
+
+/// Half-Life is a 32-bit x86 program, so must be every DLL injected into it.
+pub fn expect_x86(path: &Path) -> Result<PeInfo, LauncherError> {
+    let info = PeInfo::read(path)?;
+
+    if !info.is_x86() {
+        return Err(LauncherError::WrongPeArchitecture {
+            path: path.into(),
+            found: info.describe(),
+        });
+    }
+
+    Ok(info)
+}
+
+struct Section {
+    virtual_address: u32,
+    virtual_size: u32,
+    raw_offset: u32,
+    raw_size: u32,
+}
+
+impl Section {
+    fn parse(bytes: &[u8], offset: usize) -> Option<Self> {
+        Some(Self {
+            virtual_size: read_u32(bytes, offset + 8)?,
+            virtual_address: read_u32(bytes, offset + 12)?,
+            raw_size: read_u32(bytes, offset + 16)?,
+            raw_offset: read_u32(bytes, offset + 20)?,
+        })
+    }
+}
+
+fn rva_to_offset(sections: &[Section], rva: u32) -> Option<usize> {
+    sections
+        .iter()
+        .find(|section| {
+            let size = section.virtual_size.max(section.raw_size);
+
+            rva >= section.virtual_address && rva - section.virtual_address < size
+        })
+        // raw offsets come straight from the file and can be anything
+        .and_then(|section| {
+            (rva - section.virtual_address)
+                .checked_add(section.raw_offset)
+                .map(|offset| offset as usize)
+        })
+}
+
+/// Walks type, name and language levels of the resource tree, taking the first entry below
+/// `RT_VERSION`, then reads `VS_FIXEDFILEINFO` out of it.
+fn read_file_version(bytes: &[u8], sections: &[Section], resource_rva: u32) -> Option<[u16; 4]> {
+    let root = rva_to_offset(sections, resource_rva)?;
+
+    let find_entry = |directory: usize, id: Option<u32>| -> Option<u32> {
+        let named_count = read_u16(bytes, directory + 12)? as usize;
+        let id_count = read_u16(bytes, directory + 14)? as usize;
+
+        (0..named_count + id_count)
+            .map(|index| directory + 16 + index * 8)
+            .find(|&entry| id.is_none_or(|id| read_u32(bytes, entry) == Some(id)))
+            .and_then(|entry| read_u32(bytes, entry + 4))
+    };
+
+    const SUBDIRECTORY: u32 = 0x8000_0000;
+
+    let names = find_entry(root, Some(RT_VERSION))?;
+    let languages = find_entry(root + (names & !SUBDIRECTORY) as usize, None)?;
+    let data = find_entry(root + (languages & !SUBDIRECTORY) as usize, None)?;
+
+    // leaf is an IMAGE_RESOURCE_DATA_ENTRY pointing at VS_VERSIONINFO
+    let data = root + (data & !SUBDIRECTORY) as usize;
+    let version_info = rva_to_offset(sections, read_u32(bytes, data)?)?;
+    let version_info_size = read_u32(bytes, data + 4)? as usize;
+    let version_info = bytes.get(version_info..version_info + version_info_size)?;
+
+    // VS_FIXEDFILEINFO sits after a variable length key, find it by its signature
+    let fixed = (0..version_info.len().saturating_sub(16))
+        .step_by(4)
+        .find(|&offset| read_u32(version_info, offset) == Some(VS_FIXEDFILEINFO_SIGNATURE))?;
+
+    let version_ms = read_u32(version_info, fixed + 8)?;
+    let version_ls = read_u32(version_info, fixed + 12)?;
+
+    Some([
+        (version_ms >> 16) as u16,
+        version_ms as u16,
+        (version_ls >> 16) as u16,
+        version_ls as u16,
+    ])
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    let bytes = bytes.get(offset..offset + 2)?;
+
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?;
+
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
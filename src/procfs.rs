@@ -0,0 +1,93 @@
+use std::{collections::HashSet, fs};
+
+/// A process as seen in `/proc/<pid>/stat`.
+#[derive(Debug, Clone)]
+pub struct ProcessEntry {
+    pub pid: u32,
+    pub ppid: u32,
+    /// Executable name, cut to 15 bytes by the kernel
+    pub comm: String,
+}
+
+pub fn list_processes() -> Vec<ProcessEntry> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return vec![];
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter_map(read_stat)
+        .collect()
+}
+
+fn read_stat(pid: u32) -> Option<ProcessEntry> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+
+    // comm can contain spaces and parentheses, so it ends at the last closing one
+    let comm_start = stat.find('(')?;
+    let comm_end = stat.rfind(')')?;
+    let comm = stat.get(comm_start + 1..comm_end)?.to_owned();
+
+    // after comm: state, ppid, ...
+    let ppid = stat
+        .get(comm_end + 1..)?
+        .split_whitespace()
+        .nth(1)?
+        .parse()
+        .ok()?;
+
+    Some(ProcessEntry { pid, ppid, comm })
+}
+
+/// `root` and every process below it.
+pub fn process_tree(root: u32) -> Vec<u32> {
+    let processes = list_processes();
+    let mut tree = vec![root];
+    let mut seen = HashSet::from([root]);
+    let mut index = 0;
+
+    while let Some(&parent) = tree.get(index) {
+        for process in &processes {
+            if process.ppid == parent && seen.insert(process.pid) {
+                tree.push(process.pid);
+            }
+        }
+
+        index += 1;
+    }
+
+    tree
+}
+
+/// Files mapped into a process, from `/proc/<pid>/maps`.
+pub fn mapped_files(pid: u32) -> Vec<String> {
+    let Ok(maps) = fs::read_to_string(format!("/proc/{pid}/maps")) else {
+        return vec![];
+    };
+
+    let mut files = maps
+        .lines()
+        // address, permissions, offset, device and inode come before the path
+        .filter_map(|line| line.splitn(6, char::is_whitespace).nth(5))
+        .map(str::trim)
+        .filter(|path| path.starts_with('/'))
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+
+    files.dedup();
+    files
+}
+
+/// Arguments a process was started with, from `/proc/<pid>/cmdline`.
+pub fn command_line(pid: u32) -> Vec<String> {
+    let Ok(cmdline) = fs::read(format!("/proc/{pid}/cmdline")) else {
+        return vec![];
+    };
+
+    cmdline
+        .split(|&byte| byte == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect()
+}
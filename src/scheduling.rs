@@ -0,0 +1,181 @@
+//! Priority, CPU affinity and I/O priority of the game, set by the launcher itself so nothing
+//! like `nice`, `taskset` or `ionice` has to be installed.
+
+use crate::{config::Config, error::LauncherError};
+
+/// CPUs that fit in the affinity mask of the platform
+#[cfg(not(windows))]
+const MAX_CPUS: usize = libc::CPU_SETSIZE as usize;
+#[cfg(windows)]
+const MAX_CPUS: usize = usize::BITS as usize;
+
+/// Parses a CPU list like `0-3,6`. An empty list leaves affinity alone.
+pub fn parse_cpu_list(list: &str) -> Result<Vec<usize>, LauncherError> {
+    let invalid = || LauncherError::InvalidCpuList {
+        list: list.to_owned(),
+    };
+
+    let mut cpus = vec![];
+
+    for part in list
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+    {
+        let (first, last) = match part.split_once('-') {
+            Some((first, last)) => (first.trim(), last.trim()),
+            None => (part, part),
+        };
+
+        let first: usize = first.parse().map_err(|_| invalid())?;
+        let last: usize = last.parse().map_err(|_| invalid())?;
+
+        if first > last || last >= MAX_CPUS {
+            return Err(invalid());
+        }
+
+        cpus.extend(first..=last);
+    }
+
+    cpus.sort_unstable();
+    cpus.dedup();
+
+    Ok(cpus)
+}
+
+/// I/O scheduling class, see `ioprio_set(2)`.
+#[cfg(not(windows))]
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IoClass {
+    /// Whatever the kernel derives from the nice level
+    #[default]
+    Default,
+    RealTime,
+    BestEffort,
+    Idle,
+}
+
+#[cfg(not(windows))]
+impl IoClass {
+    pub const ALL: [Self; 4] = [Self::Default, Self::RealTime, Self::BestEffort, Self::Idle];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Default => "Default",
+            Self::RealTime => "Real-time",
+            Self::BestEffort => "Best-effort",
+            Self::Idle => "Idle",
+        }
+    }
+
+    /// Whether the class has levels, 0 being the highest and 7 the lowest
+    pub fn has_level(&self) -> bool {
+        matches!(self, Self::RealTime | Self::BestEffort)
+    }
+
+    fn ioprio(&self, level: u8) -> Option<libc::c_int> {
+        const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+
+        let class = match self {
+            Self::Default => return None,
+            Self::RealTime => 1,
+            Self::BestEffort => 2,
+            Self::Idle => 3,
+        };
+
+        Some(class << IOPRIO_CLASS_SHIFT | libc::c_int::from(level.min(7)))
+    }
+}
+
+/// Sets everything up in the forked child right before it runs the game or its runtime, so the
+/// whole process tree inherits it.
+#[cfg(not(windows))]
+pub fn apply(config: &Config, cmd: &mut std::process::Command) -> Result<(), LauncherError> {
+    use std::{io, os::unix::process::CommandExt};
+
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+
+    let nice = config.nice;
+    let cpus = parse_cpu_list(&config.cpu_affinity)?;
+    let ioprio = config.io_class.ioprio(config.io_level);
+
+    if nice == 0 && cpus.is_empty() && ioprio.is_none() {
+        return Ok(());
+    }
+
+    let mut cpu_set = unsafe { std::mem::zeroed::<libc::cpu_set_t>() };
+
+    for &cpu in &cpus {
+        unsafe { libc::CPU_SET(cpu, &mut cpu_set) };
+    }
+
+    let set_affinity = !cpus.is_empty();
+
+    // only async-signal-safe calls from here on, this runs between fork and exec
+    unsafe {
+        cmd.pre_exec(move || {
+            if nice != 0 && libc::setpriority(libc::PRIO_PROCESS, 0, nice) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            if set_affinity
+                && libc::sched_setaffinity(0, size_of::<libc::cpu_set_t>(), &cpu_set) != 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+
+            if let Some(ioprio) = ioprio
+                && libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, ioprio) != 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+
+            Ok(())
+        });
+    }
+
+    Ok(())
+}
+
+/// Whether any of the settings differ from what the game would get anyway.
+#[cfg(not(windows))]
+pub fn is_configured(config: &Config) -> bool {
+    config.nice != 0 || !config.cpu_affinity.is_empty() || config.io_class != IoClass::Default
+}
+
+/// Applies to the game while it is still suspended. Windows has priority classes instead of
+/// nice levels, so ranges of nice levels map to them.
+#[cfg(windows)]
+pub fn apply(
+    config: &Config,
+    process: windows::Win32::Foundation::HANDLE,
+) -> Result<(), LauncherError> {
+    use windows::Win32::System::Threading::{
+        ABOVE_NORMAL_PRIORITY_CLASS, BELOW_NORMAL_PRIORITY_CLASS, HIGH_PRIORITY_CLASS,
+        IDLE_PRIORITY_CLASS, SetPriorityClass, SetProcessAffinityMask,
+    };
+
+    let priority_class = match config.nice {
+        ..=-15 => Some(HIGH_PRIORITY_CLASS),
+        -14..=-1 => Some(ABOVE_NORMAL_PRIORITY_CLASS),
+        0 => None,
+        1..=14 => Some(BELOW_NORMAL_PRIORITY_CLASS),
+        15.. => Some(IDLE_PRIORITY_CLASS),
+    };
+
+    let cpus = parse_cpu_list(&config.cpu_affinity)?;
+
+    unsafe {
+        if let Some(priority_class) = priority_class {
+            SetPriorityClass(process, priority_class)?;
+        }
+
+        if !cpus.is_empty() {
+            let mask = cpus.iter().fold(0usize, |mask, cpu| mask | 1 << cpu);
+
+            SetProcessAffinityMask(process, mask)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,410 @@
+use std::{
+    ffi::OsString,
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    backend,
+    config::{Config, launcher_dir},
+    crash,
+    error::{ErrorContext, Hint, LauncherError},
+    history::{History, LaunchRecord},
+    hooks,
+    launch::LaunchPlan,
+    status::Level,
+};
+
+/// Something that happened during a launch, sent back to the UI.
+pub enum SessionEvent {
+    Status(String),
+    Error(LauncherError, Hint),
+    Exited(ExitStatus),
+    Crashed { status: ExitStatus, bundle: PathBuf },
+}
+
+impl fmt::Display for SessionEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionEvent::Status(status) => write!(f, "{status}"),
+            SessionEvent::Error(err, _) => write!(f, "{err}"),
+            SessionEvent::Exited(status) => write!(f, "Half-Life exited with {status}"),
+            SessionEvent::Crashed { status, bundle } => write!(
+                f,
+                "Half-Life crashed with {status}, crash bundle saved to {}",
+                bundle.display()
+            ),
+        }
+    }
+}
+
+impl SessionEvent {
+    /// How the event shows in the status log.
+    pub fn level(&self) -> Level {
+        match self {
+            SessionEvent::Status(_) => Level::Info,
+            SessionEvent::Exited(status) if status.code == Some(0) => Level::Info,
+            SessionEvent::Exited(_) => Level::Warn,
+            SessionEvent::Error(..) | SessionEvent::Crashed { .. } => Level::Error,
+        }
+    }
+}
+
+/// How the game process ended. Only one of the two is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExitStatus {
+    pub code: Option<i32>,
+    pub signal: Option<i32>,
+}
+
+impl From<std::process::ExitStatus> for ExitStatus {
+    fn from(value: std::process::ExitStatus) -> Self {
+        #[cfg(unix)]
+        let signal = std::os::unix::process::ExitStatusExt::signal(&value);
+
+        #[cfg(not(unix))]
+        let signal = None;
+
+        Self {
+            code: value.code(),
+            signal,
+        }
+    }
+}
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.code, self.signal) {
+            (Some(code), _) => write!(f, "code {code}"),
+            (None, Some(signal)) => write!(f, "signal {signal}"),
+            (None, None) => write!(f, "unknown status"),
+        }
+    }
+}
+
+const OUTPUT_LOG_DIR: &str = "logs";
+
+/// How many game output files are kept around, older ones are removed
+const KEPT_OUTPUT_LOGS: usize = 20;
+
+/// Everything the game printed, in a file of its own for every launch. The game writes there
+/// itself, so it keeps running when the launcher is closed.
+#[derive(Default)]
+pub struct OutputLog {
+    path: Option<PathBuf>,
+}
+
+impl OutputLog {
+    /// A new file under `logs` next to the launcher, along with it opened for the game to write
+    /// to.
+    pub fn create() -> Result<(Self, File), LauncherError> {
+        let dir = launcher_dir().join(OUTPUT_LOG_DIR);
+
+        fs::create_dir_all(&dir).context("create game output folder", &dir)?;
+        remove_old_logs(&dir);
+
+        let timestamp = jiff::Timestamp::now().strftime("%Y%m%d-%H%M%S");
+
+        for attempt in 1.. {
+            let name = match attempt {
+                1 => format!("game-{timestamp}.log"),
+                attempt => format!("game-{timestamp}-{attempt}.log"),
+            };
+            let path = dir.join(name);
+
+            // launches within the same second get a number
+            match OpenOptions::new().append(true).create_new(true).open(&path) {
+                Ok(file) => return Ok((Self { path: Some(path) }, file)),
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err).context("create game output file", &path),
+            }
+        }
+
+        unreachable!("one of the names is free")
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Whatever the game has printed so far, empty if there is nothing to read.
+    fn lines(&self) -> Vec<String> {
+        let Some(path) = &self.path else {
+            return vec![];
+        };
+
+        // the game writes whatever it likes, it does not have to be UTF-8
+        match fs::read(path) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes)
+                .lines()
+                .map(str::to_owned)
+                .collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    pub fn find(&self, predicate: impl Fn(&str) -> bool) -> Option<String> {
+        self.lines().into_iter().find(|line| predicate(line))
+    }
+
+    pub fn tail(&self, count: usize) -> Vec<String> {
+        let mut lines = self.lines();
+
+        lines.drain(..lines.len().saturating_sub(count));
+        lines
+    }
+}
+
+/// Keeps the newest [`KEPT_OUTPUT_LOGS`] minus one, making room for the one about to be created.
+/// Names start with the time, so they sort by age.
+fn remove_old_logs(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    let mut logs = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "log"))
+        .collect::<Vec<_>>();
+
+    logs.sort();
+
+    for log in logs.iter().rev().skip(KEPT_OUTPUT_LOGS - 1) {
+        // still in use by a running game, or gone already, either way nothing to do
+        let _ = fs::remove_file(log);
+    }
+}
+
+/// What hooks get to know about the launch they run around.
+#[derive(Debug, Clone)]
+pub struct SessionInfo {
+    /// One-based, as shown in the UI
+    pub profile: usize,
+    pub hlexe: PathBuf,
+    pub gamemod: String,
+    pub started: SystemTime,
+    pub ended: Option<SystemTime>,
+    pub exit_status: Option<ExitStatus>,
+    /// Why the launch did not get to an exit status
+    pub error: Option<String>,
+}
+
+impl SessionInfo {
+    pub fn new(config: &Config, profile_index: usize) -> Self {
+        Self {
+            profile: profile_index + 1,
+            hlexe: config.hlexe.clone(),
+            gamemod: config.gamemod.clone(),
+            started: SystemTime::now(),
+            ended: None,
+            exit_status: None,
+            error: None,
+        }
+    }
+
+    pub fn finish(&mut self, exit_status: ExitStatus) {
+        self.ended = Some(SystemTime::now());
+        self.exit_status = Some(exit_status);
+    }
+
+    pub fn fail(&mut self, err: &LauncherError) {
+        self.ended = Some(SystemTime::now());
+        self.error = Some(err.to_string());
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        self.ended?.duration_since(self.started).ok()
+    }
+
+    /// Environment variables describing the session, prefixed with `BXT_LAUNCHER_`.
+    pub fn env(&self) -> Vec<(String, OsString)> {
+        let unix_time = |time: SystemTime| {
+            time.duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+                .to_string()
+                .into()
+        };
+
+        let mut env = vec![
+            ("BXT_LAUNCHER_PROFILE", self.profile.to_string().into()),
+            ("BXT_LAUNCHER_HLEXE", self.hlexe.clone().into()),
+            ("BXT_LAUNCHER_GAMEMOD", self.gamemod.clone().into()),
+            ("BXT_LAUNCHER_STARTED", unix_time(self.started)),
+        ];
+
+        if let Some(ended) = self.ended {
+            env.push(("BXT_LAUNCHER_ENDED", unix_time(ended)));
+        }
+
+        if let Some(duration) = self.duration() {
+            env.push((
+                "BXT_LAUNCHER_DURATION",
+                duration.as_secs().to_string().into(),
+            ));
+        }
+
+        if let Some(exit_status) = self.exit_status {
+            if let Some(code) = exit_status.code {
+                env.push(("BXT_LAUNCHER_EXIT_CODE", code.to_string().into()));
+            }
+
+            if let Some(signal) = exit_status.signal {
+                env.push(("BXT_LAUNCHER_EXIT_SIGNAL", signal.to_string().into()));
+            }
+        }
+
+        if let Some(error) = &self.error {
+            env.push(("BXT_LAUNCHER_ERROR", error.clone().into()));
+        }
+
+        env.into_iter()
+            .map(|(key, value)| (key.to_owned(), value))
+            .collect()
+    }
+}
+
+/// Sends events to the UI and wakes it up so they show without waiting for input.
+struct Reporter {
+    sender: Sender<SessionEvent>,
+    ctx: egui::Context,
+}
+
+impl Reporter {
+    fn send(&self, event: SessionEvent) {
+        // the UI might be gone already, nothing to tell then
+        let _ = self.sender.send(event);
+        self.ctx.request_repaint();
+    }
+
+    fn status(&self, status: impl Into<String>) {
+        self.send(SessionEvent::Status(status.into()));
+    }
+
+    fn error(&self, err: LauncherError, config: &Config) {
+        let hint = err.hint(config);
+        self.send(SessionEvent::Error(err, hint));
+    }
+}
+
+/// Runs the whole launch on its own thread: pre-launch hooks, the game until it exits, then
+/// post-exit hooks. Post-exit hooks also run when the launch fails after a pre-launch hook
+/// succeeded, with `BXT_LAUNCHER_ERROR` set. The receiver disconnects once everything is done.
+///
+/// `plan` is run as it is instead of one built from `config`, to repeat an earlier launch.
+pub fn start(
+    config: Config,
+    plan: Option<LaunchPlan>,
+    profile_index: usize,
+    ctx: egui::Context,
+) -> Receiver<SessionEvent> {
+    let (sender, receiver) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        let reporter = Reporter { sender, ctx };
+
+        if let Err(err) = run(&config, plan, profile_index, &reporter) {
+            reporter.error(err, &config);
+        }
+    });
+
+    receiver
+}
+
+fn run(
+    config: &Config,
+    plan: Option<LaunchPlan>,
+    profile_index: usize,
+    reporter: &Reporter,
+) -> Result<(), LauncherError> {
+    let config = config.trim();
+    let mut session = SessionInfo::new(&config, profile_index);
+
+    let (hooks_succeeded, pre_launch) = hooks::run_pre_launch(&config, &session);
+
+    match pre_launch.and_then(|()| play(&config, plan, &mut session, reporter)) {
+        Ok(()) => (),
+        // nothing was set up that post-exit hooks would have to put back
+        Err(err) if hooks_succeeded == 0 => return Err(err),
+        Err(err) => {
+            session.fail(&err);
+            reporter.error(err, &config);
+        }
+    }
+
+    // a failing hook should not hide how the launch went
+    for err in hooks::run_post_exit(&config, &session) {
+        reporter.error(err, &config);
+    }
+
+    Ok(())
+}
+
+/// Runs the game until it exits and records how that went.
+fn play(
+    config: &Config,
+    plan: Option<LaunchPlan>,
+    session: &mut SessionInfo,
+    reporter: &Reporter,
+) -> Result<(), LauncherError> {
+    let mut game = match plan {
+        Some(plan) => backend::run_plan(config, plan)?,
+        None => backend::run_bxt(config)?,
+    };
+    match game.output().path() {
+        Some(path) => reporter.status(format!(
+            "Running (pid {}), output goes to {}",
+            game.pid(),
+            path.display()
+        )),
+        None => reporter.status(format!("Running (pid {})", game.pid())),
+    }
+
+    // the game keeps running unless it got stuck and should be killed, so this is only reported
+    match game.wait_for_bxt(config) {
+        Ok(true) => reporter.status("BXT loaded"),
+        Ok(false) => (),
+        Err(err) => {
+            let timed_out = matches!(err, LauncherError::StartupTimeout { .. });
+            reporter.error(err, config);
+
+            // still wait for it, a game that could not be killed keeps running and is recorded
+            // like any other
+            if timed_out
+                && config.kill_on_timeout
+                && let Err(err) = game.kill()
+            {
+                reporter.error(err, config);
+            }
+        }
+    }
+
+    let exit_status = game.wait()?;
+    session.finish(exit_status);
+
+    if crash::is_crash(session) {
+        match crash::write_bundle(session, config, game.plan(), game.output()) {
+            Ok(bundle) => reporter.send(SessionEvent::Crashed {
+                status: exit_status,
+                bundle,
+            }),
+            Err(err) => {
+                reporter.send(SessionEvent::Exited(exit_status));
+                reporter.error(err, config);
+            }
+        }
+    } else {
+        reporter.send(SessionEvent::Exited(exit_status));
+    }
+
+    if let Err(err) = History::append(LaunchRecord::new(session, config, game.plan())) {
+        reporter.error(err, config);
+    }
+
+    Ok(())
+}
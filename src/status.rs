@@ -0,0 +1,100 @@
+use std::{collections::VecDeque, fmt, time::SystemTime};
+
+/// How many entries are kept around
+const STATUS_LOG_ENTRIES: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    pub fn label(self) -> &'static str {
+        match self {
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+        }
+    }
+}
+
+pub struct StatusEntry {
+    pub time: SystemTime,
+    pub level: Level,
+    pub message: String,
+}
+
+impl StatusEntry {
+    /// Time of day in UTC, for the log panel.
+    pub fn short_time(&self) -> String {
+        format_time(self.time, "%H:%M:%S")
+    }
+}
+
+impl fmt::Display for StatusEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} [{}] {}",
+            format_time(self.time, "%Y-%m-%d %H:%M:%S UTC"),
+            self.level.label(),
+            self.message
+        )
+    }
+}
+
+/// What the launcher has been doing, oldest first.
+#[derive(Default)]
+pub struct StatusLog(VecDeque<StatusEntry>);
+
+impl StatusLog {
+    pub fn push(&mut self, level: Level, message: impl Into<String>) {
+        if self.0.len() == STATUS_LOG_ENTRIES {
+            self.0.pop_front();
+        }
+
+        self.0.push_back(StatusEntry {
+            time: SystemTime::now(),
+            level,
+            message: message.into(),
+        });
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(Level::Info, message);
+    }
+
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.push(Level::Warn, message);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(Level::Error, message);
+    }
+
+    pub fn latest(&self) -> Option<&StatusEntry> {
+        self.0.back()
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &StatusEntry> {
+        self.0.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// The whole log with full timestamps, one entry per line, to paste into bug reports.
+    pub fn to_text(&self) -> String {
+        self.0.iter().map(|entry| format!("{entry}\n")).collect()
+    }
+}
+
+fn format_time(time: SystemTime, format: &str) -> String {
+    match jiff::Timestamp::try_from(time) {
+        Ok(timestamp) => timestamp.strftime(format).to_string(),
+        Err(_) => "?".to_owned(),
+    }
+}
@@ -0,0 +1,319 @@
+use std::{
+    collections::HashSet,
+    env,
+    path::{Path, PathBuf},
+};
+
+pub const HALF_LIFE_APP_ID: &str = "70";
+
+/// Steam installation roots, native and Flatpak
+#[cfg(not(windows))]
+const STEAM_ROOTS: &[&str] = &[
+    "~/.steam/steam",
+    "~/.var/app/com.valvesoftware.Steam/.local/share/Steam",
+    "~/.local/share/Steam",
+];
+
+#[cfg(not(windows))]
+pub fn expand_home(path_str: &str) -> Option<PathBuf> {
+    let Some(path_str) = path_str.strip_prefix("~/") else {
+        return Some(PathBuf::from(path_str));
+    };
+
+    let home = env::var("HOME").ok()?;
+
+    Some(PathBuf::from(home).join(path_str))
+}
+
+#[cfg(not(windows))]
+pub fn get_steam_roots() -> Vec<PathBuf> {
+    let roots = STEAM_ROOTS
+        .iter()
+        .filter_map(|path_str| expand_home(path_str));
+
+    dedup_dirs(roots)
+}
+
+#[cfg(windows)]
+pub fn get_steam_roots() -> Vec<PathBuf> {
+    let roots = ["ProgramFiles(x86)", "ProgramFiles"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .map(|program_files| PathBuf::from(program_files).join("Steam"));
+
+    dedup_dirs(roots)
+}
+
+/// Keeps existing directories, dropping the ones that resolve to the same place.
+///
+/// `~/.steam/steam` is usually a symlink to one of the other roots.
+fn dedup_dirs(paths: impl IntoIterator<Item = PathBuf>) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+
+    paths
+        .into_iter()
+        .filter(|path| path.is_dir())
+        .filter(|path| seen.insert(path.canonicalize().unwrap_or_else(|_| path.clone())))
+        .collect()
+}
+
+/// Every Steam library folder, read from `steamapps/libraryfolders.vdf` of each Steam root.
+pub fn get_library_folders() -> Vec<PathBuf> {
+    let mut libraries = vec![];
+
+    for root in get_steam_roots() {
+        // the root itself is always a library even if the file is missing
+        libraries.push(root.clone());
+
+        let Ok(file) = std::fs::read_to_string(root.join("steamapps/libraryfolders.vdf")) else {
+            continue;
+        };
+
+        let Some(vdf) = Vdf::parse(&file) else {
+            continue;
+        };
+
+        let Some(folders) = vdf.get("libraryfolders") else {
+            continue;
+        };
+
+        for (key, folder) in folders.entries() {
+            // older format has the path directly as the value
+            if let Some(path) = folder.as_str() {
+                if key.parse::<usize>().is_ok() {
+                    libraries.push(PathBuf::from(path));
+                }
+
+                continue;
+            }
+
+            if let Some(path) = folder.get("path").and_then(Vdf::as_str) {
+                libraries.push(PathBuf::from(path));
+            }
+        }
+    }
+
+    dedup_dirs(libraries)
+}
+
+/// Install directories of an app, one for each library that has its `appmanifest_<appid>.acf`.
+pub fn find_app_installs(app_id: &str) -> Vec<PathBuf> {
+    get_library_folders()
+        .into_iter()
+        .filter_map(|library| find_app_install_in_library(&library, app_id))
+        .collect()
+}
+
+fn find_app_install_in_library(library: &Path, app_id: &str) -> Option<PathBuf> {
+    let steamapps = library.join("steamapps");
+    let manifest = std::fs::read_to_string(steamapps.join(format!("appmanifest_{app_id}.acf")));
+    let manifest = Vdf::parse(&manifest.ok()?)?;
+
+    let install_dir = manifest.get("AppState")?.get("installdir")?.as_str()?;
+    let path = steamapps.join("common").join(install_dir);
+
+    path.is_dir().then_some(path)
+}
+
+/// Half-Life executables found in Steam libraries.
+pub fn find_half_life_installs(hl_exe_file_name: &str) -> Vec<PathBuf> {
+    find_app_installs(HALF_LIFE_APP_ID)
+        .into_iter()
+        .map(|install| install.join(hl_exe_file_name))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+#[cfg(not(windows))]
+const PROTON_SCRIPT: &str = "proton";
+
+/// System-wide locations for custom compatibility tools, on top of the ones in Steam roots
+#[cfg(not(windows))]
+const COMPATIBILITY_TOOLS_DIRS: &[&str] = &[
+    "/usr/share/steam/compatibilitytools.d",
+    "/usr/local/share/steam/compatibilitytools.d",
+];
+
+#[cfg(not(windows))]
+#[derive(Debug, Clone)]
+pub struct ProtonInstall {
+    pub name: String,
+    /// Directory containing the `proton` script
+    pub path: PathBuf,
+}
+
+/// Proton versions installed through Steam as well as custom builds in `compatibilitytools.d`.
+#[cfg(not(windows))]
+pub fn find_proton_installs() -> Vec<ProtonInstall> {
+    let official = get_library_folders().into_iter().flat_map(|library| {
+        list_dirs(&library.join("steamapps/common"))
+            .into_iter()
+            .filter(|dir| {
+                dir.file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with("Proton"))
+            })
+    });
+
+    let custom = get_steam_roots()
+        .into_iter()
+        .map(|root| root.join("compatibilitytools.d"))
+        .chain(COMPATIBILITY_TOOLS_DIRS.iter().map(PathBuf::from))
+        .flat_map(|dir| list_dirs(&dir));
+
+    dedup_dirs(official.chain(custom))
+        .into_iter()
+        .filter(|dir| dir.join(PROTON_SCRIPT).is_file())
+        .map(|path| ProtonInstall {
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            path,
+        })
+        .collect()
+}
+
+#[cfg(not(windows))]
+fn list_dirs(path: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return vec![];
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect()
+}
+
+/// `steamapps/compatdata/<appid>` of the library a game file is installed in.
+#[cfg(not(windows))]
+pub fn get_compat_data_path(game_file: &Path, app_id: &str) -> Option<PathBuf> {
+    game_file
+        .ancestors()
+        .find(|path| path.file_name().is_some_and(|name| name == "steamapps"))
+        .map(|steamapps| steamapps.join("compatdata").join(app_id))
+}
+
+/// Valve KeyValues text format, as used by `.vdf` and `.acf` files.
+#[derive(Debug, Clone)]
+pub enum Vdf {
+    Value(String),
+    Section(Vec<(String, Vdf)>),
+}
+
+impl Vdf {
+    /// Parses a whole file as the top level section.
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut tokens = tokenize(s)?.into_iter();
+        let res = parse_section(&mut tokens, true)?;
+
+        Some(res)
+    }
+
+    /// Looks up a key case-insensitively, like Steam does.
+    pub fn get(&self, key: &str) -> Option<&Vdf> {
+        self.entries()
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value)
+    }
+
+    pub fn entries(&self) -> &[(String, Vdf)] {
+        match self {
+            Vdf::Value(_) => &[],
+            Vdf::Section(entries) => entries,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Vdf::Value(value) => Some(value),
+            Vdf::Section(_) => None,
+        }
+    }
+}
+
+enum Token {
+    String(String),
+    Open,
+    Close,
+}
+
+fn tokenize(s: &str) -> Option<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => tokens.push(Token::Open),
+            '}' => tokens.push(Token::Close),
+            '/' if chars.peek() == Some(&'/') => {
+                // comment until end of line
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '"' => {
+                let mut value = String::new();
+
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            'n' => value.push('\n'),
+                            't' => value.push('\t'),
+                            c => value.push(c),
+                        },
+                        c => value.push(c),
+                    }
+                }
+
+                tokens.push(Token::String(value));
+            }
+            c if c.is_whitespace() => (),
+            // unquoted token, up to the next whitespace or brace
+            c => {
+                let mut value = String::from(c);
+
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '{' || c == '}' || c == '"' {
+                        break;
+                    }
+
+                    value.push(c);
+                    chars.next();
+                }
+
+                tokens.push(Token::String(value));
+            }
+        }
+    }
+
+    Some(tokens)
+}
+
+fn parse_section(tokens: &mut impl Iterator<Item = Token>, top_level: bool) -> Option<Vdf> {
+    let mut entries = vec![];
+
+    loop {
+        let key = match tokens.next() {
+            Some(Token::String(key)) => key,
+            Some(Token::Close) if !top_level => break,
+            None if top_level => break,
+            _ => return None,
+        };
+
+        let value = match tokens.next()? {
+            Token::String(value) => Vdf::Value(value),
+            Token::Open => parse_section(tokens, false)?,
+            Token::Close => return None,
+        };
+
+        entries.push((key, value));
+    }
+
+    Some(Vdf::Section(entries))
+}
@@ -0,0 +1,164 @@
+//! Problems with a profile, found while it is being edited rather than once Run is pressed.
+
+use std::{
+    collections::HashMap,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    backend,
+    config::Config,
+    error::{Field, LauncherError},
+};
+
+/// Files come and go without the profile changing, so it is checked again this often
+const RECHECK_PERIOD: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Might still launch
+    Warning,
+    /// Cannot launch like this
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// `None` for problems there is no setting to point at.
+pub type Problems = HashMap<Option<Field>, Problem>;
+
+/// Everything that can be told about `config` without launching it, at most one problem per
+/// field. Looks at the filesystem, so keep it off the UI thread.
+pub fn check(config: &Config) -> Problems {
+    let config = config.trim();
+    let launcher = backend::launcher_for(&config);
+
+    let mut problems = Problems::new();
+    let mut error = |err: LauncherError| {
+        problems.entry(err.hint(&config).field).or_insert(Problem {
+            severity: Severity::Error,
+            message: err.to_string(),
+        });
+    };
+
+    // exactly what Run goes through, which stops at the first error
+    if let Err(err) = backend::launch_plan(&config) {
+        error(err);
+    }
+
+    // so that every broken file is marked and not only the first
+    for path in config.game_files() {
+        if !path.as_os_str().is_empty()
+            && let Err(err) = backend::check_game_file(path, launcher.as_ref())
+        {
+            error(err);
+        }
+    }
+
+    if let Err(err) = crate::scheduling::parse_cpu_list(&config.cpu_affinity) {
+        error(err);
+    }
+
+    let mut warning = |field, message: &str| {
+        problems.entry(Some(field)).or_insert(Problem {
+            severity: Severity::Warning,
+            message: message.to_owned(),
+        });
+    };
+
+    if !config.gamemod.is_empty()
+        && let Ok(hl_root) = config.hl_root()
+        && hl_root.is_dir()
+        && !hl_root.join(&config.gamemod).is_dir()
+    {
+        warning(Field::Gamemod, "No folder of this name next to the game");
+    }
+
+    if config.kill_on_timeout && config.startup_timeout == 0 {
+        warning(
+            Field::StartupTimeout,
+            "0 waits forever, so the game is never killed",
+        );
+    }
+
+    #[cfg(not(windows))]
+    if config.nice < 0 && unsafe { libc::geteuid() } != 0 {
+        warning(
+            Field::Priority,
+            "Below 0 fails unless the launcher has CAP_SYS_NICE",
+        );
+    }
+
+    #[cfg(not(windows))]
+    if config.use_wine && !config.wine_prefix.as_os_str().is_empty() && !config.wine_prefix.is_dir()
+    {
+        warning(
+            Field::WinePrefix,
+            "Does not exist yet, Wine creates a new prefix there",
+        );
+    }
+
+    problems
+}
+
+/// Checks the profile being edited on a background thread, again whenever it changes and every
+/// [`RECHECK_PERIOD`].
+#[derive(Default)]
+pub struct Validator {
+    checked: Option<(Config, Instant)>,
+    pending: Option<Receiver<Problems>>,
+    problems: Problems,
+}
+
+impl Validator {
+    /// Picks up the result of the last check, and starts another one if `config` is not what
+    /// was checked or that was a while ago. Only one check runs at a time.
+    pub fn update(&mut self, config: &Config, ctx: &egui::Context) {
+        if let Some(pending) = &self.pending {
+            match pending.try_recv() {
+                Ok(problems) => self.problems = problems,
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => (),
+            }
+
+            self.pending = None;
+        }
+
+        let stale = match &self.checked {
+            Some((checked, at)) => checked != config || at.elapsed() >= RECHECK_PERIOD,
+            None => true,
+        };
+
+        if stale {
+            let (sender, receiver) = mpsc::channel();
+            let config = config.clone();
+            let ctx = ctx.clone();
+
+            self.checked = Some((config.clone(), Instant::now()));
+            self.pending = Some(receiver);
+
+            std::thread::spawn(move || {
+                // the UI might be gone already, nothing to tell then
+                let _ = sender.send(check(&config));
+                ctx.request_repaint();
+            });
+        }
+
+        ctx.request_repaint_after(RECHECK_PERIOD);
+    }
+
+    pub fn problems(&self) -> &Problems {
+        &self.problems
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Problem> {
+        self.problems
+            .values()
+            .filter(|problem| problem.severity == Severity::Error)
+    }
+}
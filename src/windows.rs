@@ -1,138 +1,327 @@
-use crate::{config::Config, error::LauncherError};
+use std::{ffi::OsStr, mem, os::windows::ffi::OsStrExt, path::Path, time::Duration};
 
-pub fn run_bxt(config: &Config) -> Result<(), LauncherError> {
-    use std::{mem, path::Path};
+use windows::Win32::Foundation::{
+    CloseHandle, HANDLE, WAIT_EVENT, WAIT_FAILED, WAIT_OBJECT_0, WAIT_TIMEOUT,
+};
+use windows::Win32::System::Threading::{
+    CREATE_SUSPENDED, CreateEventW, CreateProcessW, DETACHED_PROCESS, GetExitCodeProcess, INFINITE,
+    PROCESS_INFORMATION, ResetEvent, ResumeThread, STARTUPINFOW, TerminateProcess,
+    WaitForMultipleObjects, WaitForSingleObject,
+};
+use windows::core::{PCWSTR, PWSTR};
 
-    use std::{ffi::OsStr, os::windows::ffi::OsStrExt};
-    use windows::Win32::Foundation::*;
-    use windows::Win32::System::Threading::*;
-    use windows::core::*;
+use crate::{
+    backend::GameLauncher,
+    config::Config,
+    error::{ErrorContext, LauncherError},
+    inject::{self, InjectionBackend, Readiness},
+    launch::{LaunchPlan, split_windows_args, windows_command_line},
+    session::{ExitStatus, OutputLog},
+};
 
-    // bxt-rs and BunnymodXT will fire this event when it is done loading
-    const EVENT_NAME: &str = "BunnymodXT-Injector";
+pub struct GameProcess {
+    process: HANDLE,
+    pid: u32,
+    plan: LaunchPlan,
+    // the game is detached, there is no output to read
+    output: OutputLog,
+    // what went wrong while the game was still suspended, if it was let run anyway
+    load_error: Option<LauncherError>,
+}
 
-    fn to_wide(s: &str) -> Vec<u16> {
-        OsStr::new(s)
-            .encode_wide()
-            // add null terminator
-            .chain(std::iter::once(0))
-            .collect()
+impl GameProcess {
+    pub fn pid(&self) -> u32 {
+        self.pid
     }
 
-    let config = config.trim();
+    pub fn plan(&self) -> &LaunchPlan {
+        &self.plan
+    }
 
-    // validate config
-    config.validate()?;
+    pub fn output(&self) -> &OutputLog {
+        &self.output
+    }
 
-    let Config {
-        hlexe,
-        bxt,
-        enable_bxt,
-        bxt_rs,
-        enable_bxt_rs,
-        gamemod,
-        extras,
-    } = config;
+    pub fn wait(&mut self) -> Result<ExitStatus, LauncherError> {
+        let mut code = 0u32;
 
-    let mut bxt_dlls_to_inject: Vec<&Path> = vec![];
+        unsafe {
+            if WaitForSingleObject(self.process, INFINITE) == WAIT_FAILED {
+                return Err(windows::core::Error::from_thread())
+                    .context("wait for", &self.plan.program);
+            }
 
-    // always inject bxt-rs before BunnymodXT
-    // bxt-rs
-    if enable_bxt_rs {
-        let path = Path::new(bxt_rs.as_str());
+            GetExitCodeProcess(self.process, &mut code)
+                .context("get the exit code of", &self.plan.program)?;
+        }
 
-        if path.exists() && path.is_file() {
-            bxt_dlls_to_inject.push(path);
-        } else {
-            return Err(LauncherError::FileDoesNotExist { path: path.into() });
+        Ok(ExitStatus {
+            code: Some(code as i32),
+            signal: None,
+        })
+    }
+}
+
+impl GameProcess {
+    /// `WindowsLauncher` already waits for every DLL to signal it has loaded before resuming the game,
+    /// this only reports how that went.
+    pub fn wait_for_bxt(&mut self, config: &Config) -> Result<bool, LauncherError> {
+        if let Some(err) = self.load_error.take() {
+            return Err(err);
         }
+
+        let config = config.trim();
+
+        Ok(config.enable_bxt || config.enable_bxt_rs)
     }
 
-    // BunnymodXT
-    if enable_bxt {
-        let path = Path::new(bxt.as_str());
+    pub fn kill(&mut self) -> Result<(), LauncherError> {
+        unsafe { TerminateProcess(self.process, 1).context("kill", &self.plan.program)? };
 
-        if path.exists() && path.is_file() {
-            bxt_dlls_to_inject.push(path);
-        } else {
-            return Err(LauncherError::FileDoesNotExist { path: path.into() });
+        Ok(())
+    }
+}
+
+impl Drop for GameProcess {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseHandle(self.process);
         }
     }
+}
+
+/// `hl.exe` started suspended, with BunnymodXT, bxt-rs and plugins injected before it resumes.
+pub struct WindowsLauncher;
 
-    // spawn process
-    let process_path = to_wide(&hlexe);
-    let gamemod = if gamemod.is_empty() {
-        "valve"
-    } else {
-        &gamemod
-    };
-
-    let arguments = format!("-game {gamemod} {extras}");
-    let mut arguments = to_wide(&arguments); // mutable for mutable pointer
-
-    let mut si = STARTUPINFOW::default();
-    si.cb = mem::size_of::<STARTUPINFOW>() as u32;
-    let mut pi = PROCESS_INFORMATION::default();
-
-    unsafe {
-        CreateProcessW(
-            PCWSTR(process_path.as_ptr()),       // application
-            Some(PWSTR(arguments.as_mut_ptr())), // arguments
-            None,
-            None,
-            false,
-            // suspended to load bxt-rs at Memory_Init()
-            CREATE_SUSPENDED | DETACHED_PROCESS,
-            None,
-            None,
-            &si,
-            &mut pi,
+impl GameLauncher for WindowsLauncher {
+    fn name(&self) -> &'static str {
+        "Windows"
+    }
+
+    fn uses_windows_files(&self) -> bool {
+        true
+    }
+
+    fn build_plan(&self, config: &Config) -> Result<LaunchPlan, LauncherError> {
+        let gamemod = if config.gamemod.is_empty() {
+            "valve"
+        } else {
+            &config.gamemod
+        };
+
+        let mut plan = LaunchPlan::new(&config.hlexe);
+        plan.arg("-game")
+            .arg(gamemod)
+            .args(split_windows_args(&config.extras));
+
+        Ok(plan)
+    }
+
+    fn spawn(&self, config: &Config, plan: LaunchPlan) -> Result<GameProcess, LauncherError> {
+        let mut backend = Win32Backend::new(config)?;
+
+        let load_error = inject::run(
+            &mut backend,
+            &plan,
+            &inject::injection_order(config),
+            config.startup_timeout(),
+            config.kill_on_timeout,
         )?;
+
+        let Some(pi) = backend.process.take() else {
+            unreachable!("the game is spawned when injection succeeds");
+        };
+
+        unsafe {
+            let _ = CloseHandle(pi.hThread);
+        }
+
+        // process handle is kept to wait for the game, it is closed on drop
+        Ok(GameProcess {
+            process: pi.hProcess,
+            pid: pi.dwProcessId,
+            plan,
+            output: OutputLog::default(),
+            load_error,
+        })
     }
+}
 
-    // needs to wait for DLL to be loaded
-    // this event is emitted by BunnymodXT and bxt-rs upon finishing loading
-    let event_name = to_wide(EVENT_NAME);
-    let resume_event = unsafe { CreateEventW(None, false, false, PCWSTR(event_name.as_ptr())) }?;
+/// The [`inject::INJECT_FLAG`] mode, used by the Linux launcher inside Wine and Proton. Waits for
+/// the game so whatever started the injector sees it exit, and returns its exit code.
+pub fn run_injector(config: &Config, plan: LaunchPlan) -> Result<i32, LauncherError> {
+    let mut game = WindowsLauncher.spawn(config, plan)?;
 
-    // inject
-    for dll in bxt_dlls_to_inject {
-        let target_process = dll_syringe::process::OwnedProcess::from_pid(pi.dwProcessId)?;
-        let syringe = dll_syringe::Syringe::for_process(target_process);
-        let _injected_payload = syringe.inject(dll)?;
+    if let Err(err) = game.wait_for_bxt(config) {
+        eprintln!("{err}");
+    }
+
+    Ok(game.wait()?.code.unwrap_or(1))
+}
+
+fn to_wide(s: impl AsRef<OsStr>) -> Vec<u16> {
+    s.as_ref()
+        .encode_wide()
+        // add null terminator
+        .chain(std::iter::once(0))
+        .collect()
+}
+
+/// Injection through `CreateProcessW`, dll-syringe and the event BunnymodXT and bxt-rs fire.
+struct Win32Backend<'a> {
+    config: &'a Config,
+    resume_event: HANDLE,
+    process: Option<PROCESS_INFORMATION>,
+}
+
+impl<'a> Win32Backend<'a> {
+    fn new(config: &'a Config) -> Result<Self, LauncherError> {
+        // bxt-rs and BunnymodXT will fire this event when it is done loading
+        const EVENT_NAME: &str = "BunnymodXT-Injector";
+
+        let event_name = to_wide(EVENT_NAME);
+        let resume_event =
+            unsafe { CreateEventW(None, false, false, PCWSTR(event_name.as_ptr())) }?;
+
+        Ok(Self {
+            config,
+            resume_event,
+            process: None,
+        })
+    }
+
+    fn process(&self) -> Result<&PROCESS_INFORMATION, LauncherError> {
+        self.process
+            .as_ref()
+            .ok_or_else(|| LauncherError::InjectionFailed {
+                reason: "Game is not started".to_owned(),
+            })
+    }
+}
+
+impl InjectionBackend for Win32Backend<'_> {
+    fn spawn_suspended(&mut self, plan: &LaunchPlan) -> Result<(), LauncherError> {
+        let process_path = to_wide(&plan.program);
+        let mut arguments = to_wide(windows_command_line(plan)); // mutable for mutable pointer
+
+        let mut si = STARTUPINFOW::default();
+        si.cb = mem::size_of::<STARTUPINFOW>() as u32;
+        let mut pi = PROCESS_INFORMATION::default();
 
         unsafe {
-            if WaitForSingleObject(resume_event, INFINITE) == WAIT_FAILED {
-                return Err(LauncherError::InjectionFailed {
-                    reason: "Failed to wait for resume event".to_owned(),
-                });
-            }
+            CreateProcessW(
+                PCWSTR(process_path.as_ptr()),       // application
+                Some(PWSTR(arguments.as_mut_ptr())), // arguments
+                None,
+                None,
+                false,
+                // suspended to load bxt-rs at Memory_Init()
+                CREATE_SUSPENDED | DETACHED_PROCESS,
+                None,
+                None,
+                &si,
+                &mut pi,
+            )
+            .context("start", &plan.program)?;
+        }
 
+        self.process = Some(pi);
+
+        crate::scheduling::apply(self.config, pi.hProcess)?;
+
+        Ok(())
+    }
+
+    fn inject(&mut self, dll: &Path) -> Result<(), LauncherError> {
+        let target_process =
+            dll_syringe::process::OwnedProcess::from_pid(self.process()?.dwProcessId)
+                .context("open the game to inject", dll)?;
+        let syringe = dll_syringe::Syringe::for_process(target_process);
+        let _injected_payload = syringe.inject(dll).context("inject", dll)?;
+
+        Ok(())
+    }
+
+    fn wait_ready(&mut self, timeout: Option<Duration>) -> Result<Readiness, LauncherError> {
+        let process = self.process()?.hProcess;
+        let timeout = timeout.map_or(INFINITE, |timeout| timeout.as_millis() as u32);
+
+        // the game exiting wakes this up as well
+        let waited =
+            unsafe { WaitForMultipleObjects(&[self.resume_event, process], false, timeout) };
+
+        if waited == WAIT_OBJECT_0 {
             // need to reset event so the next dll can use it
-            ResetEvent(resume_event)?;
+            unsafe { ResetEvent(self.resume_event)? };
+
+            return Ok(Readiness::Ready);
         }
+
+        if waited == WAIT_EVENT(WAIT_OBJECT_0.0 + 1) {
+            let mut code = 0u32;
+            unsafe { GetExitCodeProcess(process, &mut code)? };
+
+            return Ok(Readiness::Exited(ExitStatus {
+                code: Some(code as i32),
+                signal: None,
+            }));
+        }
+
+        if waited == WAIT_TIMEOUT {
+            return Ok(Readiness::TimedOut);
+        }
+
+        Err(LauncherError::InjectionFailed {
+            reason: "Failed to wait for resume event".to_owned(),
+        })
     }
 
-    // // injection completes
-    // // should try freeing mutex that prevents multiple game instances
-    // unsafe {
-    //     let mutex = OpenMutexW(
-    //         SYNCHRONIZATION_SYNCHRONIZE,
-    //         false,
-    //         PCWSTR(to_wide("ValveHalfLifeLauncherMutex").as_ptr()),
-    //     );
+    fn resume(&mut self) -> Result<(), LauncherError> {
+        let thread = self.process()?.hThread;
 
-    //     if let Ok(mutex) = mutex {
-    //         ReleaseMutex(mutex)?;
-    //         CloseHandle(mutex)?;
-    //     }
-    // };
+        // // injection completes
+        // // should try freeing mutex that prevents multiple game instances
+        // unsafe {
+        //     let mutex = OpenMutexW(
+        //         SYNCHRONIZATION_SYNCHRONIZE,
+        //         false,
+        //         PCWSTR(to_wide("ValveHalfLifeLauncherMutex").as_ptr()),
+        //     );
 
-    unsafe {
-        ResumeThread(pi.hThread);
-        // CloseHandle(pi.hThread)?;
-        // CloseHandle(pi.hProcess)?;
+        //     if let Ok(mutex) = mutex {
+        //         ReleaseMutex(mutex)?;
+        //         CloseHandle(mutex)?;
+        //     }
+        // };
+
+        unsafe {
+            ResumeThread(thread);
+        }
+
+        Ok(())
     }
 
-    Ok(())
+    fn terminate(&mut self) -> Result<(), LauncherError> {
+        let Some(pi) = self.process.take() else {
+            return Ok(());
+        };
+
+        unsafe {
+            let res = TerminateProcess(pi.hProcess, 1);
+            let _ = CloseHandle(pi.hThread);
+            let _ = CloseHandle(pi.hProcess);
+
+            res?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Win32Backend<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseHandle(self.resume_event);
+        }
+    }
 }
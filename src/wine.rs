@@ -0,0 +1,211 @@
+use std::{
+    env,
+    ffi::OsString,
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    backend::{GameLauncher, GameProcess},
+    config::{Config, WineRunner},
+    error::{ErrorContext, LauncherError},
+    inject,
+    launch::{LaunchPlan, split_windows_args},
+    steam::{HALF_LIFE_APP_ID, get_compat_data_path, get_steam_roots},
+};
+
+const DEFAULT_WINE_BINARY: &str = "wine";
+
+/// `hl.exe` under a system Wine.
+pub struct WineLauncher;
+
+/// `hl.exe` under Proton, in its own prefix like Steam would do.
+pub struct ProtonLauncher;
+
+impl GameLauncher for WineLauncher {
+    fn name(&self) -> &'static str {
+        WineRunner::Wine.label()
+    }
+
+    fn uses_windows_files(&self) -> bool {
+        true
+    }
+
+    fn build_plan(&self, config: &Config) -> Result<LaunchPlan, LauncherError> {
+        let wine = find_wine(&config.wine_binary).ok_or(LauncherError::NoWine)?;
+
+        let mut plan = LaunchPlan::new(wine);
+
+        if !config.wine_prefix.as_os_str().is_empty() {
+            plan.env("WINEPREFIX", &config.wine_prefix);
+        }
+
+        add_game_args_and_env(config, &mut plan)?;
+
+        Ok(plan)
+    }
+
+    fn spawn(&self, config: &Config, plan: LaunchPlan) -> Result<GameProcess, LauncherError> {
+        crate::linux::spawn(config, plan)
+    }
+}
+
+impl GameLauncher for ProtonLauncher {
+    fn name(&self) -> &'static str {
+        WineRunner::Proton.label()
+    }
+
+    fn uses_windows_files(&self) -> bool {
+        true
+    }
+
+    fn build_plan(&self, config: &Config) -> Result<LaunchPlan, LauncherError> {
+        let mut plan =
+            proton_launch_plan(&config.hlexe, &config.proton, &config.proton_compat_data)?;
+
+        add_game_args_and_env(config, &mut plan)?;
+
+        Ok(plan)
+    }
+
+    fn spawn(&self, config: &Config, plan: LaunchPlan) -> Result<GameProcess, LauncherError> {
+        // proton wants the directory to exist before it creates the prefix inside, only done
+        // here so that looking at the plan does not leave folders behind
+        if let Some((_, compat_data)) = plan
+            .env
+            .iter()
+            .find(|(key, _)| key == "STEAM_COMPAT_DATA_PATH")
+        {
+            std::fs::create_dir_all(compat_data)
+                .context("create Proton prefix folder", compat_data)?;
+        }
+
+        crate::linux::spawn(config, plan)
+    }
+}
+
+/// Everything after Wine or Proton itself.
+///
+/// BunnymodXT, bxt-rs and plugins cannot be preloaded into a Windows process from here, so the
+/// Windows build of the launcher is run inside the same prefix to inject them, see
+/// [`inject::injector_args`]. It stays around until the game exits.
+fn add_game_args_and_env(config: &Config, plan: &mut LaunchPlan) -> Result<(), LauncherError> {
+    let hl_root = config.hl_root()?;
+
+    let libraries = config.libraries();
+
+    // plugins need the injector as much as BunnymodXT does
+    if !libraries.is_empty() {
+        if config.wine_injector.as_os_str().is_empty() {
+            return Err(LauncherError::NoWineInjector);
+        }
+
+        if !config.wine_injector.is_file() {
+            return Err(LauncherError::FileDoesNotExist {
+                path: config.wine_injector.clone(),
+            });
+        }
+
+        plan.arg(&config.wine_injector);
+        plan.args(inject::injector_args(config, dos_path));
+    }
+
+    plan.arg(dos_path(&config.hlexe));
+
+    if !config.gamemod.is_empty() {
+        plan.arg("-game").arg(&config.gamemod);
+    }
+
+    plan.args(split_windows_args(&config.extras));
+
+    if !config.wine_dll_overrides.is_empty() {
+        plan.env("WINEDLLOVERRIDES", &config.wine_dll_overrides);
+    }
+
+    if !config.wine_debug.is_empty() {
+        plan.env("WINEDEBUG", &config.wine_debug);
+    }
+
+    plan.current_dir(hl_root);
+
+    Ok(())
+}
+
+/// Path of a file outside the prefix as Windows programs see it, every Wine and Proton prefix
+/// maps `/` to `Z:`.
+fn dos_path(path: &Path) -> OsString {
+    let mut bytes = b"Z:".to_vec();
+
+    bytes.extend(
+        path.as_os_str()
+            .as_bytes()
+            .iter()
+            .map(|&byte| if byte == b'/' { b'\\' } else { byte }),
+    );
+
+    OsString::from_vec(bytes)
+}
+
+/// Proton is given its own prefix per app under `steamapps/compatdata`, same as Steam would.
+fn proton_launch_plan(
+    hlexe: &Path,
+    proton: &Path,
+    proton_compat_data: &Path,
+) -> Result<LaunchPlan, LauncherError> {
+    if proton.as_os_str().is_empty() {
+        return Err(LauncherError::NoProton);
+    }
+
+    let proton_script = proton.join("proton");
+
+    if !proton_script.is_file() {
+        return Err(LauncherError::FileDoesNotExist {
+            path: proton_script,
+        });
+    }
+
+    let compat_data = if proton_compat_data.as_os_str().is_empty() {
+        get_compat_data_path(hlexe, HALF_LIFE_APP_ID).ok_or(LauncherError::NoProtonCompatData)?
+    } else {
+        proton_compat_data.to_path_buf()
+    };
+
+    let steam_root = get_steam_roots()
+        .into_iter()
+        .next()
+        .ok_or(LauncherError::CannotFindSteam)?;
+
+    let mut plan = LaunchPlan::new(proton_script);
+
+    plan.arg("run");
+    plan.env("STEAM_COMPAT_DATA_PATH", &compat_data);
+    plan.env("STEAM_COMPAT_CLIENT_INSTALL_PATH", &steam_root);
+
+    Ok(plan)
+}
+
+/// Resolves the configured Wine binary.
+///
+/// A bare name such as `wine` or `wine-staging` is searched in `PATH`, anything with a directory
+/// component is taken as is. Empty falls back to `wine`.
+pub fn find_wine(wine_binary: &Path) -> Option<PathBuf> {
+    let path = if wine_binary.as_os_str().is_empty() {
+        Path::new(DEFAULT_WINE_BINARY)
+    } else {
+        wine_binary
+    };
+
+    if path.components().count() > 1 {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+
+    find_in_path(path)
+}
+
+pub fn find_in_path(name: impl AsRef<Path>) -> Option<PathBuf> {
+    let paths = env::var_os("PATH")?;
+
+    env::split_paths(&paths)
+        .map(|dir| dir.join(name.as_ref()))
+        .find(|path| path.is_file())
+}